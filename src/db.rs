@@ -2,6 +2,7 @@ use crate::TelegramId;
 use crate::utils::{ErrorString, ErrorType};
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
 use teloxide::types::Message;
 use tokio::sync::OnceCell;
 
@@ -12,8 +13,137 @@ pub struct Monitor {
     pub telegram_id: u64,
     pub monitor_url: String,
     pub notification_token: Option<String>,
+    /// 该实例不支持 `/api/rpc2`，使用旧版 REST API
+    pub legacy_api: bool,
+    /// 连接时 `rpc.methods` 返回的方法列表 (JSON)，为空时视为全部支持
+    pub rpc_methods: Option<String>,
+    /// 请求 Komari 时附加的 HTTP 头 (JSON 对象)，如 Cloudflare Access 的凭据
+    pub extra_headers: Option<String>,
+    /// 用户为实例设置的名称
+    pub instance_name: Option<String>,
+    pub timeout_secs: Option<i64>,
+    /// HTTP / SOCKS5 代理地址
+    pub proxy: Option<String>,
+    /// 额外信任的 CA 证书 (PEM)
+    pub ca_cert: Option<String>,
+    /// 跳过 TLS 证书校验，需用户显式开启
+    pub insecure_tls: bool,
 }
 
+/// 实例的 HTTP 客户端选项，相同选项的实例共享同一个客户端
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct HttpOptions {
+    pub timeout_secs: Option<u64>,
+    pub proxy: Option<String>,
+    pub ca_cert: Option<String>,
+    pub insecure_tls: bool,
+}
+
+impl Monitor {
+    #[must_use]
+    pub fn extra_headers(&self) -> BTreeMap<String, String> {
+        self.extra_headers
+            .as_deref()
+            .and_then(|headers| serde_json::from_str(headers).ok())
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn http_options(&self) -> HttpOptions {
+        HttpOptions {
+            timeout_secs: self.timeout_secs.and_then(|secs| u64::try_from(secs).ok()),
+            proxy: self.proxy.clone(),
+            ca_cert: self.ca_cert.clone(),
+            insecure_tls: self.insecure_tls,
+        }
+    }
+
+    /// 缓存键，包含地址及凭据，避免不同凭据的用户共享缓存
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.monitor_url,
+            self.extra_headers.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// 用户或群组的设置，`id` 为用户 ID 或群组 ID，未设置的项为空
+#[derive(Debug, Default, sqlx::FromRow, Clone)]
+pub struct SettingsRow {
+    pub id: i64,
+    pub language: Option<String>,
+    pub units: Option<String>,
+    pub throughput: Option<String>,
+    pub decimals: Option<i64>,
+    pub thousands_separator: Option<bool>,
+    pub timezone: Option<String>,
+    pub compact: Option<bool>,
+    pub default_node: Option<i64>,
+    pub alert_chat: Option<i64>,
+    pub delete_after_secs: Option<i64>,
+    pub stale_after_secs: Option<i64>,
+    pub show_hidden: Option<bool>,
+    pub agent_target: Option<String>,
+    pub weekly_report: Option<bool>,
+    /// 上次发送每周版本报告的 UNIX 时间戳
+    pub weekly_report_at: Option<i64>,
+    pub node_change_alerts: Option<bool>,
+}
+
+/// 节点清单快照中的标记行，没有节点的实例也能以此记录已保存过快照
+const INVENTORY_MARKER: &str = "";
+
+/// 上次轮询时节点的静态信息，用于检测节点的增删、重命名及配置变更
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct NodeInventoryRow {
+    pub node_uuid: String,
+    pub name: String,
+    pub cpu_cores: i64,
+    pub mem_total: i64,
+    pub disk_total: i64,
+}
+
+/// 节点上次获取到的系统与内核版本，以及最近一次变更前的版本与变更时间
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct NodeSystemRow {
+    pub node_uuid: String,
+    pub os: String,
+    pub kernel_version: String,
+    pub old_os: Option<String>,
+    pub old_kernel_version: Option<String>,
+    pub changed_at: Option<i64>,
+}
+
+const SETTINGS_MIGRATIONS: [&str; 9] = [
+    "ALTER TABLE settings ADD COLUMN throughput TEXT",
+    "ALTER TABLE settings ADD COLUMN decimals INTEGER",
+    "ALTER TABLE settings ADD COLUMN thousands_separator INTEGER",
+    "ALTER TABLE settings ADD COLUMN stale_after_secs INTEGER",
+    "ALTER TABLE settings ADD COLUMN show_hidden INTEGER",
+    "ALTER TABLE settings ADD COLUMN agent_target TEXT",
+    "ALTER TABLE settings ADD COLUMN weekly_report INTEGER",
+    "ALTER TABLE settings ADD COLUMN weekly_report_at INTEGER",
+    "ALTER TABLE settings ADD COLUMN node_change_alerts INTEGER",
+];
+
+// 在旧数据库上补充新增的列
+const MONITOR_MIGRATIONS: [&str; 4] = [
+    "ALTER TABLE monitor ADD COLUMN legacy_api INTEGER NOT NULL DEFAULT 0",
+    "ALTER TABLE monitor ADD COLUMN rpc_methods TEXT",
+    "ALTER TABLE monitor ADD COLUMN extra_headers TEXT",
+    "ALTER TABLE monitor ADD COLUMN instance_name TEXT",
+];
+
+// HTTP 客户端选项单独保存，可在连接前设置，且不会因重新连接而丢失
+const MONITOR_SELECT: &str =
+    "SELECT m.telegram_id, m.monitor_url, m.notification_token, m.legacy_api,
+            m.rpc_methods, m.extra_headers, m.instance_name, o.timeout_secs, o.proxy, o.ca_cert,
+            COALESCE(o.insecure_tls, 0) AS insecure_tls
+     FROM monitor m
+     LEFT JOIN http_options o ON o.telegram_id = m.telegram_id";
+
 pub async fn connect_db(sqlite_db_file: &str) -> Result<&Pool<Sqlite>, ErrorType> {
     DB_POOL
         .get_or_try_init(|| async {
@@ -43,26 +173,154 @@ pub async fn create_table(pool: &Pool<Sqlite>) -> Result<(), ErrorType> {
     .execute(pool)
     .await
     {
-        Err(ErrorType::DataBaseError {
+        return Err(ErrorType::DataBaseError {
             error: ErrorString::from(e.to_string()),
-        })
-    } else {
-        Ok(())
+        });
     }
+
+    migrate_table(pool, &MONITOR_MIGRATIONS).await?;
+
+    if let Err(e) = sqlx::query(
+        "CREATE TABLE IF NOT EXISTS http_options (
+             telegram_id INTEGER PRIMARY KEY,
+             timeout_secs INTEGER,
+             proxy TEXT,
+             ca_cert TEXT,
+             insecure_tls INTEGER NOT NULL DEFAULT 0
+         )",
+    )
+    .execute(pool)
+    .await
+    {
+        return Err(ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        });
+    }
+
+    if let Err(e) = sqlx::query(
+        "CREATE TABLE IF NOT EXISTS settings (
+             id INTEGER PRIMARY KEY,
+             language TEXT,
+             units TEXT,
+             timezone TEXT,
+             compact INTEGER,
+             default_node INTEGER,
+             alert_chat INTEGER,
+             delete_after_secs INTEGER
+         )",
+    )
+    .execute(pool)
+    .await
+    {
+        return Err(ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        });
+    }
+
+    migrate_table(pool, &SETTINGS_MIGRATIONS).await?;
+
+    // 节点清单快照，重启后仍可与上次轮询的结果比较
+    if let Err(e) = sqlx::query(
+        "CREATE TABLE IF NOT EXISTS node_inventory (
+             telegram_id INTEGER NOT NULL,
+             monitor_url TEXT NOT NULL,
+             node_uuid TEXT NOT NULL,
+             name TEXT NOT NULL,
+             cpu_cores INTEGER NOT NULL,
+             mem_total INTEGER NOT NULL,
+             disk_total INTEGER NOT NULL,
+             PRIMARY KEY (telegram_id, monitor_url, node_uuid)
+         )",
+    )
+    .execute(pool)
+    .await
+    {
+        return Err(ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        });
+    }
+
+    // 节点的系统与内核版本，重启后仍可判断近期是否发生变更
+    if let Err(e) = sqlx::query(
+        "CREATE TABLE IF NOT EXISTS node_systems (
+             telegram_id INTEGER NOT NULL,
+             monitor_url TEXT NOT NULL,
+             node_uuid TEXT NOT NULL,
+             os TEXT NOT NULL,
+             kernel_version TEXT NOT NULL,
+             old_os TEXT,
+             old_kernel_version TEXT,
+             changed_at INTEGER,
+             PRIMARY KEY (telegram_id, monitor_url, node_uuid)
+         )",
+    )
+    .execute(pool)
+    .await
+    {
+        return Err(ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        });
+    }
+
+    // 节点的短 ID，新增节点不会改变已有节点的 ID
+    if let Err(e) = sqlx::query(
+        "CREATE TABLE IF NOT EXISTS node_ids (
+             telegram_id INTEGER NOT NULL,
+             monitor_url TEXT NOT NULL,
+             node_uuid TEXT NOT NULL,
+             short_id INTEGER NOT NULL,
+             PRIMARY KEY (telegram_id, monitor_url, node_uuid)
+         )",
+    )
+    .execute(pool)
+    .await
+    {
+        return Err(ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        });
+    }
+
+    // 同一实例中的短 ID 不可重复，旧版本并发分配产生的重复 ID 先删除，之后重新分配
+    for statement in [
+        "DELETE FROM node_ids WHERE rowid NOT IN (
+             SELECT MIN(rowid) FROM node_ids GROUP BY telegram_id, monitor_url, short_id
+         )",
+        "CREATE UNIQUE INDEX IF NOT EXISTS node_ids_short_id
+             ON node_ids (telegram_id, monitor_url, short_id)",
+    ] {
+        if let Err(e) = sqlx::query(statement).execute(pool).await {
+            return Err(ErrorType::DataBaseError {
+                error: ErrorString::from(e.to_string()),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+async fn migrate_table(pool: &Pool<Sqlite>, migrations: &[&str]) -> Result<(), ErrorType> {
+    for migration in migrations {
+        if let Err(e) = sqlx::query(migration).execute(pool).await
+            && !e.to_string().contains("duplicate column name")
+        {
+            return Err(ErrorType::DataBaseError {
+                error: ErrorString::from(e.to_string()),
+            });
+        }
+    }
+
+    Ok(())
 }
 
 pub async fn query_monitor_by_telegram_id(
     pool: &Pool<Sqlite>,
     telegram_id: TelegramId,
 ) -> Result<Option<Monitor>, ErrorType> {
-    let monitor_result = sqlx::query_as::<_, Monitor>(
-        "SELECT telegram_id, monitor_url, notification_token
-         FROM monitor
-         WHERE telegram_id = ?",
-    )
-    .bind(telegram_id)
-    .fetch_optional(pool)
-    .await;
+    let monitor_result =
+        sqlx::query_as::<_, Monitor>(&format!("{MONITOR_SELECT} WHERE m.telegram_id = ?"))
+            .bind(telegram_id)
+            .fetch_optional(pool)
+            .await;
 
     if let Ok(monitor_result) = monitor_result {
         Ok(monitor_result)
@@ -81,12 +339,16 @@ pub async fn insert_monitor(pool: &Pool<Sqlite>, monitor: Monitor) -> Result<(),
     }
 
     if let Err(e) = sqlx::query(
-        "INSERT OR IGNORE INTO monitor (telegram_id, monitor_url, notification_token)
-         VALUES (?, ?, ?)",
+        "INSERT OR IGNORE INTO monitor (telegram_id, monitor_url, notification_token, legacy_api, rpc_methods, extra_headers, instance_name)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(monitor.telegram_id as i64)
     .bind(monitor.monitor_url)
     .bind(monitor.notification_token)
+    .bind(monitor.legacy_api)
+    .bind(monitor.rpc_methods)
+    .bind(monitor.extra_headers)
+    .bind(monitor.instance_name)
     .execute(pool)
     .await
     {
@@ -128,17 +390,445 @@ pub async fn update_notification_token(
         }),
     }
 }
-pub async fn get_all_monitors(pool: &Pool<Sqlite>) -> Result<Vec<Monitor>, ErrorType> {
-    let monitors = sqlx::query_as::<_, Monitor>(
-        "SELECT telegram_id, monitor_url, notification_token
-         FROM monitor",
+pub async fn update_extra_headers(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    extra_headers: Option<String>,
+) -> Result<(), ErrorType> {
+    sqlx::query("UPDATE monitor SET extra_headers = ? WHERE telegram_id = ?")
+        .bind(extra_headers)
+        .bind(telegram_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+pub async fn update_instance_name(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    instance_name: Option<String>,
+) -> Result<(), ErrorType> {
+    sqlx::query("UPDATE monitor SET instance_name = ? WHERE telegram_id = ?")
+        .bind(instance_name)
+        .bind(telegram_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+pub async fn update_http_options(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    options: &HttpOptions,
+) -> Result<(), ErrorType> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO http_options (telegram_id, timeout_secs, proxy, ca_cert, insecure_tls)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(telegram_id)
+    .bind(options.timeout_secs.and_then(|secs| i64::try_from(secs).ok()))
+    .bind(&options.proxy)
+    .bind(&options.ca_cert)
+    .bind(options.insecure_tls)
+    .execute(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })?;
+
+    Ok(())
+}
+
+pub async fn query_http_options(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+) -> Result<HttpOptions, ErrorType> {
+    let options = sqlx::query_as::<_, (Option<i64>, Option<String>, Option<String>, bool)>(
+        "SELECT timeout_secs, proxy, ca_cert, insecure_tls FROM http_options WHERE telegram_id = ?",
+    )
+    .bind(telegram_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })?;
+
+    Ok(options
+        .map(|(timeout_secs, proxy, ca_cert, insecure_tls)| HttpOptions {
+            timeout_secs: timeout_secs.and_then(|secs| u64::try_from(secs).ok()),
+            proxy,
+            ca_cert,
+            insecure_tls,
+        })
+        .unwrap_or_default())
+}
+
+pub async fn query_settings(
+    pool: &Pool<Sqlite>,
+    id: i64,
+) -> Result<Option<SettingsRow>, ErrorType> {
+    sqlx::query_as::<_, SettingsRow>(
+        "SELECT id, language, units, throughput, decimals, thousands_separator, timezone,
+                compact, default_node, alert_chat, delete_after_secs, stale_after_secs,
+                show_hidden, agent_target, weekly_report, weekly_report_at, node_change_alerts
+         FROM settings WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })
+}
+
+pub async fn update_settings(pool: &Pool<Sqlite>, settings: &SettingsRow) -> Result<(), ErrorType> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO settings (id, language, units, throughput, decimals, thousands_separator,
+                                           timezone, compact, default_node, alert_chat, delete_after_secs,
+                                           stale_after_secs, show_hidden, agent_target, weekly_report,
+                                           weekly_report_at, node_change_alerts)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(settings.id)
+    .bind(&settings.language)
+    .bind(&settings.units)
+    .bind(&settings.throughput)
+    .bind(settings.decimals)
+    .bind(settings.thousands_separator)
+    .bind(&settings.timezone)
+    .bind(settings.compact)
+    .bind(settings.default_node)
+    .bind(settings.alert_chat)
+    .bind(settings.delete_after_secs)
+    .bind(settings.stale_after_secs)
+    .bind(settings.show_hidden)
+    .bind(&settings.agent_target)
+    .bind(settings.weekly_report)
+    .bind(settings.weekly_report_at)
+    .bind(settings.node_change_alerts)
+    .execute(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })?;
+
+    Ok(())
+}
+
+/// 开启了每周版本报告且距上次发送已超过 `interval_secs` 的用户
+pub async fn query_due_weekly_reports(
+    pool: &Pool<Sqlite>,
+    now: i64,
+    interval_secs: i64,
+) -> Result<Vec<TelegramId>, ErrorType> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM settings
+         WHERE weekly_report = 1 AND (weekly_report_at IS NULL OR weekly_report_at <= ?)",
     )
+    .bind(now - interval_secs)
     .fetch_all(pool)
     .await
     .map_err(|e| ErrorType::DataBaseError {
         error: ErrorString::from(e.to_string()),
+    })
+}
+
+pub async fn update_weekly_report_at(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    time: i64,
+) -> Result<(), ErrorType> {
+    sqlx::query("UPDATE settings SET weekly_report_at = ? WHERE id = ?")
+        .bind(time)
+        .bind(telegram_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+pub async fn delete_settings(pool: &Pool<Sqlite>, id: i64) -> Result<(), ErrorType> {
+    sqlx::query("DELETE FROM settings WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+/// 读取已分配的节点短 ID，返回 `(节点 UUID, 短 ID)`
+pub async fn query_node_ids(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+) -> Result<Vec<(String, i64)>, ErrorType> {
+    sqlx::query_as::<_, (String, i64)>(
+        "SELECT node_uuid, short_id FROM node_ids WHERE telegram_id = ? AND monitor_url = ?",
+    )
+    .bind(telegram_id)
+    .bind(monitor_url)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })
+}
+
+/// 写入新分配的节点短 ID，节点或短 ID 已存在的记录会被忽略
+pub async fn insert_node_ids(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+    node_ids: &[(String, i64)],
+) -> Result<(), ErrorType> {
+    let mut transaction = pool.begin().await.map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
     })?;
 
+    for (node_uuid, short_id) in node_ids {
+        sqlx::query(
+            "INSERT OR IGNORE INTO node_ids (telegram_id, monitor_url, node_uuid, short_id)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(telegram_id)
+        .bind(monitor_url)
+        .bind(node_uuid)
+        .bind(short_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })
+}
+
+/// 删除节点短 ID，`keep_url` 对应实例的记录会被保留
+pub async fn delete_node_ids(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    keep_url: Option<&str>,
+) -> Result<(), ErrorType> {
+    sqlx::query("DELETE FROM node_ids WHERE telegram_id = ? AND monitor_url IS NOT ?")
+        .bind(telegram_id)
+        .bind(keep_url)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+/// 读取节点清单快照，从未保存过时返回 `None`
+pub async fn query_node_inventory(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+) -> Result<Option<Vec<NodeInventoryRow>>, ErrorType> {
+    let rows = sqlx::query_as::<_, NodeInventoryRow>(
+        "SELECT node_uuid, name, cpu_cores, mem_total, disk_total
+         FROM node_inventory WHERE telegram_id = ? AND monitor_url = ?",
+    )
+    .bind(telegram_id)
+    .bind(monitor_url)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })?;
+
+    if !rows.iter().any(|row| row.node_uuid == INVENTORY_MARKER) {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        rows.into_iter()
+            .filter(|row| row.node_uuid != INVENTORY_MARKER)
+            .collect(),
+    ))
+}
+
+/// 以 `nodes` 替换该实例的节点清单快照
+pub async fn replace_node_inventory(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+    nodes: &[NodeInventoryRow],
+) -> Result<(), ErrorType> {
+    let mut transaction = pool.begin().await.map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })?;
+
+    sqlx::query("DELETE FROM node_inventory WHERE telegram_id = ? AND monitor_url = ?")
+        .bind(telegram_id)
+        .bind(monitor_url)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    let marker = NodeInventoryRow {
+        node_uuid: INVENTORY_MARKER.to_string(),
+        name: String::new(),
+        cpu_cores: 0,
+        mem_total: 0,
+        disk_total: 0,
+    };
+
+    for node in std::iter::once(&marker).chain(nodes) {
+        sqlx::query(
+            "INSERT INTO node_inventory (telegram_id, monitor_url, node_uuid, name, cpu_cores,
+                                         mem_total, disk_total)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(telegram_id)
+        .bind(monitor_url)
+        .bind(&node.node_uuid)
+        .bind(&node.name)
+        .bind(node.cpu_cores)
+        .bind(node.mem_total)
+        .bind(node.disk_total)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })
+}
+
+/// 删除节点清单快照，`keep_url` 对应实例的记录会被保留
+pub async fn delete_node_inventory(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    keep_url: Option<&str>,
+) -> Result<(), ErrorType> {
+    sqlx::query("DELETE FROM node_inventory WHERE telegram_id = ? AND monitor_url IS NOT ?")
+        .bind(telegram_id)
+        .bind(keep_url)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+pub async fn query_node_systems(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+) -> Result<Vec<NodeSystemRow>, ErrorType> {
+    sqlx::query_as::<_, NodeSystemRow>(
+        "SELECT node_uuid, os, kernel_version, old_os, old_kernel_version, changed_at
+         FROM node_systems WHERE telegram_id = ? AND monitor_url = ?",
+    )
+    .bind(telegram_id)
+    .bind(monitor_url)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })
+}
+
+/// 写入或更新节点的系统与内核版本
+pub async fn upsert_node_systems(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+    nodes: &[NodeSystemRow],
+) -> Result<(), ErrorType> {
+    let mut transaction = pool.begin().await.map_err(|e| ErrorType::DataBaseError {
+        error: ErrorString::from(e.to_string()),
+    })?;
+
+    for node in nodes {
+        sqlx::query(
+            "INSERT OR REPLACE INTO node_systems (telegram_id, monitor_url, node_uuid, os,
+                                                  kernel_version, old_os, old_kernel_version,
+                                                  changed_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(telegram_id)
+        .bind(monitor_url)
+        .bind(&node.node_uuid)
+        .bind(&node.os)
+        .bind(&node.kernel_version)
+        .bind(&node.old_os)
+        .bind(&node.old_kernel_version)
+        .bind(node.changed_at)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })
+}
+
+/// 删除节点的系统与内核版本记录，`keep_url` 对应实例的记录会被保留
+pub async fn delete_node_systems(
+    pool: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    keep_url: Option<&str>,
+) -> Result<(), ErrorType> {
+    sqlx::query("DELETE FROM node_systems WHERE telegram_id = ? AND monitor_url IS NOT ?")
+        .bind(telegram_id)
+        .bind(keep_url)
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
+    Ok(())
+}
+
+pub async fn get_all_monitors(pool: &Pool<Sqlite>) -> Result<Vec<Monitor>, ErrorType> {
+    let monitors = sqlx::query_as::<_, Monitor>(MONITOR_SELECT)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ErrorType::DataBaseError {
+            error: ErrorString::from(e.to_string()),
+        })?;
+
     Ok(monitors)
 }
 
@@ -1,5 +1,6 @@
 use crate::db::query_monitor_by_telegram_id;
 use crate::json_rpc::create_reqwest_client;
+use crate::settings::load_settings;
 use crate::utils::ErrorType;
 use crate::{TelegramId, db};
 use axum::routing::post;
@@ -11,6 +12,7 @@ use log::{error, info};
 use std::env;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use teloxide::types::ChatId;
 use tokio::sync::Mutex;
 use urlencoding::encode;
 
@@ -86,8 +88,14 @@ pub async fn http_callback(param1: String, param2: String, param3: String, body:
         return;
     };
 
+    // 设置了通知会话时优先发送到该会话
+    let chat_id = load_settings(telegram_id, ChatId(telegram_id))
+        .await
+        .alert_chat
+        .map_or(param3, |chat_id| chat_id.0.to_string());
+
     let url = format!(
-        "https://api.telegram.org/bot{tg_token}/sendMessage?chat_id={param3}&text={}",
+        "https://api.telegram.org/bot{tg_token}/sendMessage?chat_id={chat_id}&text={}",
         encode(format!("[{title}] {message}").as_str())
     );
 
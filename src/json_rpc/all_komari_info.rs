@@ -1,7 +1,7 @@
 use crate::MessageString;
 use crate::db::{DB_POOL, Monitor, get_all_monitors};
-use crate::json_rpc::bytes_to_pretty_string;
-use crate::json_rpc::query::{AllInfo, get_all_info};
+use crate::json_rpc::query::{AllInfo, NODE_LIST_METHODS, get_info};
+use crate::settings::Settings;
 use crate::utils::ErrorType;
 use log::error;
 use tokio::sync::mpsc;
@@ -18,7 +18,7 @@ pub fn filter_valid_all_info(all_infos: Vec<AllInfo>) -> Vec<AllInfo> {
         .collect()
 }
 
-pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
+pub async fn get_every_one_status(settings: &Settings) -> Result<MessageString, ErrorType> {
     let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
         error: "无法获取数据库".to_string(),
     })?;
@@ -33,7 +33,7 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
         for monitor in monitors {
             let tx = tx.clone();
             tokio::spawn(async move {
-                let all_info = match get_all_info(monitor.monitor_url.as_str()).await {
+                let all_info = match get_info(&monitor, &NODE_LIST_METHODS).await {
                     Ok(all_info) => all_info,
                     Err(e) => {
                         error!("{}", e);
@@ -55,6 +55,7 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
     }
 
     let success_count = all_infos.len();
+    let fmt = settings.number_format();
 
     let all_infos = filter_valid_all_info(all_infos);
 
@@ -141,8 +142,8 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
             0.0
         };
         (
-            bytes_to_pretty_string(total_used_ram),
-            bytes_to_pretty_string(total_total_ram),
+            fmt.bytes(total_used_ram),
+            fmt.bytes(total_total_ram),
             avg_ram_usage,
         )
     };
@@ -164,8 +165,8 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
             0.0
         };
         (
-            bytes_to_pretty_string(total_used_swap),
-            bytes_to_pretty_string(total_total_swap),
+            fmt.bytes(total_used_swap),
+            fmt.bytes(total_total_swap),
             avg_swap_usage,
         )
     };
@@ -187,8 +188,8 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
             0.0
         };
         (
-            bytes_to_pretty_string(total_used_disk),
-            bytes_to_pretty_string(total_total_disk),
+            fmt.bytes(total_used_disk),
+            fmt.bytes(total_total_disk),
             avg_disk_usage,
         )
     };
@@ -213,8 +214,8 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
                 .map(|node| node.net_total_up)
                 .sum::<i64>();
             (
-                bytes_to_pretty_string(total_total_net_down),
-                bytes_to_pretty_string(total_total_net_up),
+                fmt.bytes(total_total_net_down),
+                fmt.bytes(total_total_net_up),
             )
         };
         let (total_net_down, total_net_up) = {
@@ -228,10 +229,7 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
                 .flat_map(|all_info| all_info.common_nodes_latest_status.values())
                 .map(|node| node.net_out)
                 .sum::<i64>();
-            (
-                total_net_down as f64 / 125000.0,
-                total_net_up as f64 / 125000.0,
-            )
+            (fmt.speed(total_net_down), fmt.speed(total_net_up))
         };
         let (total_tcp_connections, total_udp_connections) = {
             let total_tcp_connections = all_infos
@@ -244,7 +242,10 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
                 .flat_map(|all_info| all_info.common_nodes_latest_status.values())
                 .map(|node| node.connections_udp)
                 .sum::<i64>();
-            (total_tcp_connections, total_udp_connections)
+            (
+                fmt.integer(total_tcp_connections),
+                fmt.integer(total_udp_connections),
+            )
         };
         (
             total_total_net_down,
@@ -257,25 +258,41 @@ pub async fn get_every_one_status() -> Result<MessageString, ErrorType> {
     };
 
     let msg = format!(
-        r"@komaritgbot 总览:
+        r"@komaritgbot {overview}:
 
-本 Bot 已保存连接: {all_user_count}
-本 Bot 已成功读取: {success_count}
+{saved_label}: {all_user_count}
+{success_label}: {success_count}
 
-ONLINE: `{online_nodes_count}` / `{total_nodes_count}` `{percent_online:.2}%`
+ONLINE: `{online_nodes_count}` / `{total_nodes_count}` `{percent_online}%`
 CPU CORES: `{total_cpu_cores}`
-AVG CPU: `{avg_cpu_usage:.2}%`
-AVG LOAD: `{avg_load1:.2}` / `{avg_load5:.2}` / `{avg_load15:.2}`
+AVG CPU: `{avg_cpu_usage}%`
+AVG LOAD: `{avg_load1}` / `{avg_load5}` / `{avg_load15}`
 
-MEM: `{total_used_ram}` / `{total_total_ram}` `{avg_ram_usage:.2}%`
-SWAP: `{total_used_swap}` / `{total_total_swap}` `{avg_swap_usage:.2}%`
-DISK: `{total_used_disk}` / `{total_total_disk}` `{avg_disk_usage:.2}%`
+MEM: `{total_used_ram}` / `{total_total_ram}` `{avg_ram_usage}%`
+SWAP: `{total_used_swap}` / `{total_total_swap}` `{avg_swap_usage}%`
+DISK: `{total_used_disk}` / `{total_total_disk}` `{avg_disk_usage}%`
 
 DOWN: `{total_total_net_down}`
 UP: `{total_total_net_up}`
-DOWN SPEED: `{total_net_down:.2} Mbps`
-UP SPEED: `{total_net_up:.2} Mbps`
+DOWN SPEED: `{total_net_down}`
+UP SPEED: `{total_net_up}`
 CONN: `{total_tcp_connections} TCP` / `{total_udp_connections} UDP`",
+        percent_online = fmt.number(percent_online),
+        total_cpu_cores = fmt.integer(total_cpu_cores),
+        avg_cpu_usage = fmt.number(avg_cpu_usage),
+        avg_load1 = fmt.number(avg_load1),
+        avg_load5 = fmt.number(avg_load5),
+        avg_load15 = fmt.number(avg_load15),
+        avg_ram_usage = fmt.number(avg_ram_usage),
+        avg_swap_usage = fmt.number(avg_swap_usage),
+        avg_disk_usage = fmt.number(avg_disk_usage),
+        overview = settings.language.pick("总览", "Overview"),
+        saved_label = settings
+            .language
+            .pick("本 Bot 已保存连接", "Saved connections"),
+        success_label = settings
+            .language
+            .pick("本 Bot 已成功读取", "Fetched successfully"),
     );
 
     Ok(msg)
@@ -0,0 +1,239 @@
+use crate::MessageString;
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedMutexGuard;
+
+pub static CACHE_CONFIG: OnceLock<CacheConfig> = OnceLock::new();
+
+/// 清理过期槽位的间隔
+const SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+static SLOTS: LazyLock<Mutex<HashMap<CacheKey, Arc<tokio::sync::Mutex<Slot>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static LAST_SWEEP: LazyLock<Mutex<Instant>> = LazyLock::new(|| Mutex::new(Instant::now()));
+
+static STATS: LazyLock<Mutex<BTreeMap<&'static str, MethodStats>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub latest_status_ttl_secs: u64,
+    pub public_info_ttl_secs: u64,
+    pub nodes_ttl_secs: u64,
+    pub version_ttl_secs: u64,
+    pub rpc_ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            latest_status_ttl_secs: 3,
+            public_info_ttl_secs: 300,
+            nodes_ttl_secs: 60,
+            version_ttl_secs: 3600,
+            rpc_ttl_secs: 3600,
+        }
+    }
+}
+
+impl CacheConfig {
+    fn ttl(&self, method: &str) -> Duration {
+        let secs = match method {
+            "common:getNodesLatestStatus" => self.latest_status_ttl_secs,
+            "common:getPublicInfo" => self.public_info_ttl_secs,
+            "common:getNodes" => self.nodes_ttl_secs,
+            "common:getVersion" => self.version_ttl_secs,
+            _ => self.rpc_ttl_secs,
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    monitor: String,
+    method: &'static str,
+}
+
+#[derive(Default)]
+pub struct Slot {
+    value: Option<(Instant, Arc<dyn Any + Send + Sync>)>,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct MethodStats {
+    hits: u64,
+    coalesced: u64,
+    misses: u64,
+}
+
+/// 一次批量请求所持有的缓存槽位
+///
+/// 槽位按照方法名排序后加锁，避免方法顺序不同的并发请求死锁。并发的相同请求会在锁上等待，待第一个请求写入结果后直接读取缓存
+pub struct CachedBatch {
+    slots: Vec<(&'static str, OwnedMutexGuard<Slot>)>,
+}
+
+impl CachedBatch {
+    /// 需要向 Komari 请求的方法
+    #[must_use]
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.slots
+            .iter()
+            .filter(|(_, slot)| slot.value.is_none())
+            .map(|(method, _)| *method)
+            .collect()
+    }
+
+    pub fn store<T: Any + Send + Sync>(&mut self, method: &str, value: T) {
+        if let Some((_, slot)) = self.slots.iter_mut().find(|(m, _)| *m == method) {
+            slot.value = Some((Instant::now(), Arc::new(value)));
+        }
+    }
+
+    #[must_use]
+    pub fn get<T: Any + Send + Sync + Clone>(&self, method: &str) -> Option<T> {
+        self.slots
+            .iter()
+            .find(|(m, _)| *m == method)
+            .and_then(|(_, slot)| slot.value.as_ref())
+            .and_then(|(_, value)| value.downcast_ref::<T>())
+            .cloned()
+    }
+}
+
+/// 锁定 `monitor` (见 [`Monitor::cache_key`]) 下 `methods` 对应的缓存槽位，过期的槽位会被清空，`force` 为真时全部视为过期
+///
+/// [`Monitor::cache_key`]: crate::db::Monitor::cache_key
+pub async fn lock(monitor: &str, methods: &[&'static str], force: bool) -> CachedBatch {
+    let config = CACHE_CONFIG.get_or_init(CacheConfig::default);
+
+    let mut methods = methods.to_vec();
+    methods.sort_unstable();
+    methods.dedup();
+
+    let handles = {
+        let mut slots = SLOTS
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let mut last_sweep = LAST_SWEEP
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if last_sweep.elapsed() >= SWEEP_INTERVAL {
+            sweep(&mut slots, config);
+            *last_sweep = Instant::now();
+        }
+
+        methods
+            .iter()
+            .map(|method| {
+                let key = CacheKey {
+                    monitor: monitor.to_string(),
+                    method,
+                };
+                (*method, slots.entry(key).or_default().clone())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut batch = CachedBatch { slots: vec![] };
+    for (method, handle) in handles {
+        let (mut slot, waited) = match handle.clone().try_lock_owned() {
+            Ok(slot) => (slot, false),
+            Err(_) => (handle.lock_owned().await, true),
+        };
+
+        let fresh = !force
+            && config.enabled
+            && slot
+                .value
+                .as_ref()
+                .is_some_and(|(at, _)| at.elapsed() < config.ttl(method));
+
+        if !fresh {
+            slot.value = None;
+        }
+
+        record(method, fresh, waited);
+        batch.slots.push((method, slot));
+    }
+
+    batch
+}
+
+/// 删除已过期且未被使用的槽位，断开连接或更换了地址、请求头的实例不再占用内存
+fn sweep(slots: &mut HashMap<CacheKey, Arc<tokio::sync::Mutex<Slot>>>, config: &CacheConfig) {
+    slots.retain(|key, handle| {
+        if Arc::strong_count(handle) > 1 {
+            return true;
+        }
+        let Ok(slot) = handle.try_lock() else {
+            return true;
+        };
+        slot.value
+            .as_ref()
+            .is_some_and(|(at, _)| at.elapsed() < config.ttl(key.method))
+    });
+}
+
+fn record(method: &'static str, hit: bool, waited: bool) {
+    let mut stats = STATS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let entry = stats.entry(method).or_default();
+    match (hit, waited) {
+        (true, true) => entry.coalesced += 1,
+        (true, false) => entry.hits += 1,
+        (false, _) => entry.misses += 1,
+    }
+}
+
+#[must_use]
+pub fn cache_stats(settings: &Settings) -> MessageString {
+    let lang = settings.language;
+    let entries = SLOTS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .len();
+    let stats = STATS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone();
+
+    let mut msg = format!(
+        "{}\n\n{}: `{entries}`\n\n",
+        lang.pick("Komari 请求缓存统计", "Komari request cache stats"),
+        lang.pick("缓存条目", "Cache entries")
+    );
+
+    if stats.is_empty() {
+        msg.push_str(lang.pick("暂无请求", "No requests yet"));
+        return msg;
+    }
+
+    for (method, stat) in stats {
+        let total = stat.hits + stat.coalesced + stat.misses;
+        let hit_rate = if total > 0 {
+            (stat.hits + stat.coalesced) as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        };
+        msg.push_str(&format!(
+            "`{method}`\nHIT: `{hits}` / COALESCED: `{coalesced}` / MISS: `{misses}` `{hit_rate:.2}%`\n",
+            hits = stat.hits,
+            coalesced = stat.coalesced,
+            misses = stat.misses,
+        ));
+    }
+
+    msg
+}
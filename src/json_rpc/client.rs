@@ -0,0 +1,183 @@
+use crate::db::Monitor;
+use crate::json_rpc::reqwest_client_for;
+use crate::json_rpc::resilience::with_retry;
+use crate::utils::ErrorType;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const JSON_RPC_VERSION: &str = "2.0";
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonRpcRequestBase {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    pub id: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonRpcResponseBase {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub result: Option<Value>,
+    pub error: Option<JsonRpcError>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    fn into_error_type(self, method: &str) -> ErrorType {
+        ErrorType::JsonRpcError {
+            method: method.to_string(),
+            code: self.code,
+            message: self.message,
+            data: self.data.map(|data| data.to_string()),
+        }
+    }
+}
+
+// Komari 在批量请求整体失败时可能只返回单个错误对象
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonRpcResponseBody {
+    Batch(Vec<JsonRpcResponseBase>),
+    Single(JsonRpcResponseBase),
+}
+
+/// Komari `/api/rpc2` 的 JSON-RPC 客户端
+pub struct JsonRpcClient {
+    monitor: Monitor,
+}
+
+impl JsonRpcClient {
+    #[must_use]
+    pub fn new(monitor: &Monitor) -> Self {
+        Self {
+            monitor: monitor.clone(),
+        }
+    }
+
+    pub async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<T, ErrorType> {
+        let mut batch = self.batch();
+        let id = batch.add(method, params);
+
+        batch.send().await?.take(id)
+    }
+
+    #[must_use]
+    pub fn batch(&self) -> BatchRequest<'_> {
+        BatchRequest {
+            client: self,
+            requests: vec![],
+        }
+    }
+}
+
+pub struct BatchRequest<'a> {
+    client: &'a JsonRpcClient,
+    requests: Vec<JsonRpcRequestBase>,
+}
+
+impl BatchRequest<'_> {
+    /// 添加一个请求，返回其 id
+    pub fn add(&mut self, method: &str, params: Option<Value>) -> i64 {
+        let id = (self.requests.len() + 1) as i64;
+        self.requests.push(JsonRpcRequestBase {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            method: method.to_string(),
+            params,
+            id,
+        });
+        id
+    }
+
+    pub async fn send(self) -> Result<BatchResponse, ErrorType> {
+        let client = reqwest_client_for(&self.client.monitor).await?;
+
+        let url = format!("{}/api/rpc2", self.client.monitor.monitor_url);
+
+        let headers = self.client.monitor.extra_headers();
+
+        let response_body = with_retry(&self.client.monitor.monitor_url, || async {
+            let mut request = client.post(&url).json(&self.requests);
+            for (name, value) in &headers {
+                request = request.header(name, value);
+            }
+
+            let response = request
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)?;
+
+            Ok(response.json::<JsonRpcResponseBody>().await?)
+        })
+        .await?;
+
+        let json_rpc_response_body = match response_body {
+            JsonRpcResponseBody::Batch(responses) => responses,
+            JsonRpcResponseBody::Single(response) => {
+                if let (None, Some(error)) = (response.id, response.error.clone()) {
+                    return Err(error.into_error_type("batch"));
+                }
+                vec![response]
+            }
+        };
+
+        Ok(BatchResponse {
+            methods: self
+                .requests
+                .into_iter()
+                .map(|request| (request.id, request.method))
+                .collect(),
+            responses: json_rpc_response_body
+                .into_iter()
+                .filter_map(|response| response.id.map(|id| (id, response)))
+                .collect(),
+        })
+    }
+}
+
+pub struct BatchResponse {
+    methods: HashMap<i64, String>,
+    responses: HashMap<i64, JsonRpcResponseBase>,
+}
+
+impl BatchResponse {
+    /// 取出 id 对应的结果并反序列化
+    pub fn take<T: DeserializeOwned>(&mut self, id: i64) -> Result<T, ErrorType> {
+        let method = self.methods.get(&id).cloned().unwrap_or_default();
+
+        let response = self
+            .responses
+            .remove(&id)
+            .ok_or_else(|| ErrorType::JsonParseError {
+                error: format!("Json 解析错误: 未找到 {method} (id 为 {id}) 的响应"),
+            })?;
+
+        if let Some(error) = response.error {
+            return Err(error.into_error_type(&method));
+        }
+
+        serde_json::from_value(response.result.unwrap_or_default()).map_err(|e| {
+            ErrorType::JsonParseError {
+                error: format!("Json 解析错误: {method} (id 为 {id}) 的响应: {e}"),
+            }
+        })
+    }
+}
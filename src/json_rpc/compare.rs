@@ -0,0 +1,210 @@
+use crate::json_rpc::get_node_id::{NodeSort, SortedNodeList, get_node_id_list};
+use crate::json_rpc::query::{
+    AllInfo, CommonGetNodesLatestStatusSingle, CommonGetNodesSingle, STATUS_METHODS,
+};
+use crate::json_rpc::search::{MAX_CHOICES, search_nodes};
+use crate::settings::Settings;
+use crate::utils::{ErrorType, MAX_PAGE_CHARS, escaped_len};
+use crate::{MessageString, TelegramId};
+use std::time::Duration;
+
+/// 对比的一行，每个节点对应一个值
+struct Row {
+    title: &'static str,
+    values: Vec<String>,
+}
+
+/// 解析一个参数为节点，可为短 ID 或搜索关键词
+fn resolve(arg: &str, all_info: &AllInfo, node_list: &SortedNodeList) -> Result<usize, ErrorType> {
+    if let Ok(node_id) = arg.parse::<i32>()
+        && let Some(index) = node_list.iter().position(|(id, _, _)| *id == node_id)
+    {
+        return Ok(index);
+    }
+
+    match search_nodes(arg, all_info, node_list)?.as_slice() {
+        [(node_id, _)] => Ok(node_list
+            .iter()
+            .position(|(id, _, _)| id == node_id)
+            .unwrap_or_default()),
+        matches => Err(ErrorType::GeneralError {
+            error: format!(
+                "{arg} 匹配到多个节点: {}，请使用节点 ID 或更精确的关键词",
+                matches
+                    .iter()
+                    .take(MAX_CHOICES)
+                    .map(|(node_id, name)| format!("{node_id} {name}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }),
+    }
+}
+
+fn usage_rows(
+    statuses: &[Option<&CommonGetNodesLatestStatusSingle>],
+    settings: &Settings,
+) -> Vec<Row> {
+    let fmt = settings.number_format();
+    let lang = settings.language;
+    let row = |title, value: &dyn Fn(&CommonGetNodesLatestStatusSingle) -> String| Row {
+        title,
+        values: statuses
+            .iter()
+            .map(|status| match status {
+                Some(status) if status.online => value(status),
+                Some(_) => lang.pick("离线", "offline").to_string(),
+                None => lang.pick("从未上报", "never reported").to_string(),
+            })
+            .collect(),
+    };
+
+    vec![
+        row("CPU", &|status| format!("{}%", fmt.number(status.cpu))),
+        row("RAM", &|status| {
+            format!(
+                "{} / {}",
+                fmt.bytes(status.ram),
+                fmt.bytes(status.ram_total)
+            )
+        }),
+        row("DISK", &|status| {
+            format!(
+                "{} / {}",
+                fmt.bytes(status.disk),
+                fmt.bytes(status.disk_total)
+            )
+        }),
+        row("LOAD", &|status| {
+            format!(
+                "{} / {} / {}",
+                fmt.number(status.load),
+                fmt.number(status.load5),
+                fmt.number(status.load15)
+            )
+        }),
+        row("NET", &|status| {
+            format!(
+                "↓{} ↑{}",
+                fmt.speed(status.net_in),
+                fmt.speed(status.net_out)
+            )
+        }),
+        row("UPTIME", &|status| {
+            settings.format_duration(Duration::from_secs(status.uptime.max(0) as u64))
+        }),
+    ]
+}
+
+fn hardware_rows(nodes: &[&CommonGetNodesSingle], settings: &Settings) -> Vec<Row> {
+    let fmt = settings.number_format();
+    let row = |title, value: &dyn Fn(&CommonGetNodesSingle) -> String| Row {
+        title,
+        values: nodes.iter().map(|node| value(node)).collect(),
+    };
+
+    vec![
+        row("CPU NAME", &|node| node.cpu_name.clone()),
+        row("CPU CORES", &|node| fmt.integer(node.cpu_cores)),
+        row("MEM TOTAL", &|node| fmt.bytes(node.mem_total)),
+        row("DISK TOTAL", &|node| fmt.bytes(node.disk_total)),
+        row("VIRTUALIZATION", &|node| node.virtualization.clone()),
+        row("ARCH", &|node| node.arch.clone()),
+        row("OS", &|node| node.os.clone()),
+        row("KERNEL", &|node| node.kernel_version.clone()),
+    ]
+}
+
+/// 对比多个节点的硬件与当前负载
+///
+/// 以指标分段，每段中每个节点一行。内容超出单条消息时拆分为多页，每页单独发送
+pub async fn compare_nodes(
+    telegram_id: TelegramId,
+    args: &[String],
+    settings: &Settings,
+) -> Result<Vec<MessageString>, ErrorType> {
+    let (_, all_info, node_list) =
+        get_node_id_list(telegram_id, &STATUS_METHODS, settings, NodeSort::Id).await?;
+
+    let mut selected = vec![];
+    for arg in args {
+        let index = resolve(arg, &all_info, &node_list)?;
+        if !selected.contains(&index) {
+            selected.push(index);
+        }
+    }
+
+    if selected.len() < 2 {
+        return Err(ErrorType::GeneralError {
+            error: String::from("请至少指定两个不同的节点"),
+        });
+    }
+    if selected.len() > MAX_CHOICES {
+        return Err(ErrorType::GeneralError {
+            error: format!("最多同时对比 {MAX_CHOICES} 个节点"),
+        });
+    }
+
+    let mut labels = vec![];
+    let mut nodes = vec![];
+    let mut statuses = vec![];
+    for index in selected {
+        let (node_id, node_uuid, status) = &node_list[index];
+        let Some(node) = all_info.common_nodes.get(node_uuid) else {
+            continue;
+        };
+        labels.push(format!("{node_id} {}", node.name.replace('`', "'")));
+        nodes.push(node);
+        statuses.push(status.as_ref());
+    }
+
+    let rows = hardware_rows(&nodes, settings)
+        .into_iter()
+        .chain(usage_rows(&statuses, settings))
+        .collect::<Vec<_>>();
+
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let mut pages = vec![];
+    let mut page = String::new();
+    for row in rows {
+        let mut section = format!("{}\n", row.title);
+        for (label, value) in labels.iter().zip(&row.values) {
+            section.push_str(&format!(
+                "  {label:<label_width$}  {}\n",
+                value.replace('`', "'")
+            ));
+        }
+
+        if !page.is_empty() && escaped_len(&page) + escaped_len(&section) > MAX_PAGE_CHARS {
+            pages.push(std::mem::take(&mut page));
+        }
+        page.push_str(&section);
+        page.push('\n');
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+
+    let total = pages.len();
+    Ok(pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let title = if total > 1 {
+                format!(
+                    "{} ({}/{total})",
+                    all_info.common_public_info.sitename,
+                    index + 1
+                )
+            } else {
+                all_info.common_public_info.sitename.clone()
+            };
+            format!("{title}\n```\n{}\n```", page.trim_end())
+        })
+        .collect())
+}
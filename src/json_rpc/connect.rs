@@ -1,67 +1,181 @@
 use crate::db::{DB_POOL, Monitor};
-use crate::json_rpc::bytes_to_pretty_string;
-use crate::json_rpc::query::get_all_info;
+use crate::json_rpc::client::JsonRpcClient;
+use crate::json_rpc::legacy;
+use crate::json_rpc::query::{
+    CONNECT_METHODS, CommonGetPublicInfo, PUBLIC_INFO, RPC_METHODS, RpcMethods, get_info_uncached,
+};
+use crate::settings::Settings;
+use crate::ssrf;
 use crate::utils::ErrorType;
 use crate::{MessageString, TelegramId, db};
+use reqwest::Url;
+use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
 
+/// 校验并规范化 Komari 地址
+///
+/// 保留反向代理的子路径及 user:pass@，仅去掉查询参数与末尾的 /
+pub async fn normalize_komari_url(http_url: &str) -> Result<String, ErrorType> {
+    let mut url = Url::parse(http_url.trim()).map_err(|e| ErrorType::GeneralError {
+        error: format!("无效的 URL: {e}"),
+    })?;
+
+    if url.host_str().is_none() || !matches!(url.scheme(), "http" | "https") {
+        return Err(ErrorType::GeneralError {
+            error: String::from("无效的 URL，仅支持 http:// 或 https://"),
+        });
+    }
+
+    ssrf::check_url(&url).await?;
+
+    url.set_query(None);
+    url.set_fragment(None);
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+async fn new_monitor(
+    db: &Pool<Sqlite>,
+    http_url: String,
+    extra_headers: Option<String>,
+    telegram_id: TelegramId,
+) -> Result<Monitor, ErrorType> {
+    let options = db::query_http_options(db, telegram_id).await?;
+
+    Ok(Monitor {
+        telegram_id: telegram_id as u64,
+        monitor_url: http_url,
+        notification_token: None,
+        legacy_api: false,
+        rpc_methods: None,
+        extra_headers,
+        instance_name: None,
+        timeout_secs: options
+            .timeout_secs
+            .and_then(|secs| i64::try_from(secs).ok()),
+        proxy: options.proxy,
+        ca_cert: options.ca_cert,
+        insecure_tls: options.insecure_tls,
+    })
+}
+
+/// 连接前检查站点，返回站点信息 (含是否为私有站点)
+pub async fn check_site(
+    http_url: String,
+    telegram_id: TelegramId,
+) -> Result<CommonGetPublicInfo, ErrorType> {
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    let mut monitor = new_monitor(db, http_url, None, telegram_id).await?;
+    (monitor.legacy_api, monitor.rpc_methods) = detect_api(&monitor).await?;
+
+    let all_info = get_info_uncached(&monitor, &[PUBLIC_INFO]).await?;
+
+    Ok(all_info.common_public_info)
+}
+
+/// 连接到 Komari 并保存，`extra_headers` 为空时沿用同一地址已保存的 HTTP 头
 pub async fn connect_komari_with_update_db(
     http_url: String,
+    extra_headers: BTreeMap<String, String>,
     telegram_id: TelegramId,
+    settings: &Settings,
 ) -> Result<MessageString, ErrorType> {
     let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
         error: "无法获取数据库".to_string(),
     })?;
 
-    let all_info = get_all_info(&http_url).await?;
+    let saved = db::query_monitor_by_telegram_id(db, telegram_id)
+        .await?
+        .filter(|monitor| monitor.monitor_url == http_url);
 
-    let monitor = Monitor {
-        telegram_id: telegram_id as u64,
-        monitor_url: http_url,
-        notification_token: None,
+    let extra_headers = if extra_headers.is_empty() {
+        saved
+            .as_ref()
+            .and_then(|monitor| monitor.extra_headers.clone())
+    } else {
+        serde_json::to_string(&extra_headers).ok()
     };
 
+    let mut monitor = new_monitor(db, http_url, extra_headers, telegram_id).await?;
+    monitor.instance_name = saved.and_then(|monitor| monitor.instance_name);
+
+    (monitor.legacy_api, monitor.rpc_methods) = detect_api(&monitor).await?;
+
+    let all_info = get_info_uncached(&monitor, &CONNECT_METHODS).await?;
+
     db::delete_monitor(db, telegram_id).await?;
 
-    db::insert_monitor(db, monitor).await?;
+    db::insert_monitor(db, monitor.clone()).await?;
 
+    // 连接到其他实例时旧的节点 ID 不再有效
+    db::delete_node_ids(db, telegram_id, Some(&monitor.monitor_url)).await?;
+    db::delete_node_inventory(db, telegram_id, Some(&monitor.monitor_url)).await?;
+    db::delete_node_systems(db, telegram_id, Some(&monitor.monitor_url)).await?;
+
+    let lang = settings.language;
+    let fmt = settings.number_format();
     let msg: MessageString = format!(
-        "成功读取 Komari 服务信息！
-站点名称：`{site_name}`
-站点详情：`{site_description}`
-站点版本: `{site_version}`
-
-节点数量：`{nodes_count}`
-CPU 核心总数：`{cores_count}`
-内存总量：`{memory_total}`
-交换分区总量：`{swap_total}`
-硬盘总量：`{disk_total}`",
+        "{title}
+{site_name_label}: `{site_name}`
+{site_description_label}: `{site_description}`
+{site_version_label}: `{site_version}`
+{api_type_label}: `{api_type}`
+
+{nodes_count_label}: `{nodes_count}`
+{cores_count_label}: `{cores_count}`
+{memory_total_label}: `{memory_total}`
+{swap_total_label}: `{swap_total}`
+{disk_total_label}: `{disk_total}`",
+        title = lang.pick("成功读取 Komari 服务信息！", "Connected to Komari!"),
+        site_name_label = lang.pick("站点名称", "Site name"),
+        site_description_label = lang.pick("站点详情", "Description"),
+        site_version_label = lang.pick("站点版本", "Version"),
+        api_type_label = lang.pick("接口类型", "API"),
+        nodes_count_label = lang.pick("节点数量", "Nodes"),
+        cores_count_label = lang.pick("CPU 核心总数", "CPU cores"),
+        memory_total_label = lang.pick("内存总量", "Memory"),
+        swap_total_label = lang.pick("交换分区总量", "Swap"),
+        disk_total_label = lang.pick("硬盘总量", "Disk"),
         site_name = all_info.common_public_info.sitename,
         site_description = all_info.common_public_info.description,
-        site_version = format!(
-            "{}-{}",
-            all_info.common_version.version, all_info.common_version.hash
-        ),
+        site_version = if all_info.common_version.version.is_empty() {
+            lang.pick("未知", "Unknown").to_string()
+        } else {
+            format!(
+                "{}-{}",
+                all_info.common_version.version, all_info.common_version.hash
+            )
+        },
+        api_type = if monitor.legacy_api {
+            lang.pick("REST API (旧版)", "REST API (legacy)")
+        } else {
+            "JSON-RPC"
+        },
         nodes_count = all_info.common_nodes.len(),
-        cores_count = all_info
-            .common_nodes
-            .iter()
-            .map(|node| node.1.cpu_cores)
-            .sum::<i64>(),
-        memory_total = bytes_to_pretty_string(
+        cores_count = fmt.integer(
+            all_info
+                .common_nodes
+                .iter()
+                .map(|node| node.1.cpu_cores)
+                .sum::<i64>()
+        ),
+        memory_total = fmt.bytes(
             all_info
                 .common_nodes
                 .iter()
                 .map(|node| node.1.mem_total)
                 .sum::<i64>()
         ),
-        swap_total = bytes_to_pretty_string(
+        swap_total = fmt.bytes(
             all_info
                 .common_nodes
                 .iter()
                 .map(|node| node.1.swap_total)
                 .sum::<i64>()
         ),
-        disk_total = bytes_to_pretty_string(
+        disk_total = fmt.bytes(
             all_info
                 .common_nodes
                 .iter()
@@ -73,7 +187,27 @@ CPU 核心总数：`{cores_count}`
     Ok(msg)
 }
 
-pub async fn update_connection(telegram_id: TelegramId) -> Result<MessageString, ErrorType> {
+/// 检测实例支持的接口，`/api/rpc2` 不可用时回退到旧版 REST API
+///
+/// 返回是否使用旧版 API 以及 `rpc.methods` 的 JSON
+async fn detect_api(monitor: &Monitor) -> Result<(bool, Option<String>), ErrorType> {
+    let client = JsonRpcClient::new(monitor);
+
+    match client.call::<RpcMethods>(RPC_METHODS, None).await {
+        Ok(methods) => Ok((false, serde_json::to_string(&methods).ok())),
+        // `/api/rpc2` 存在但不支持 rpc.methods，按全部支持处理
+        Err(ErrorType::JsonRpcError { .. }) => Ok((false, None)),
+        Err(e) => match legacy::probe(monitor).await {
+            Ok(()) => Ok((true, None)),
+            Err(_) => Err(e),
+        },
+    }
+}
+
+pub async fn update_connection(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
     let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
         error: "无法获取数据库".to_string(),
     })?;
@@ -82,7 +216,9 @@ pub async fn update_connection(telegram_id: TelegramId) -> Result<MessageString,
         .await?
         .ok_or(ErrorType::UserNotConnected)?;
 
-    let connection = connect_komari_with_update_db(monitor.monitor_url, telegram_id).await?;
+    let connection =
+        connect_komari_with_update_db(monitor.monitor_url, BTreeMap::new(), telegram_id, settings)
+            .await?;
 
     Ok(connection)
 }
@@ -0,0 +1,231 @@
+use crate::settings::{Throughput, Units};
+
+const BINARY_UNITS: [&str; 9] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB", "YiB"];
+const SI_UNITS: [&str; 9] = ["B", "kB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
+const BIT_RATE_UNITS: [&str; 7] = ["bps", "kbps", "Mbps", "Gbps", "Tbps", "Pbps", "Ebps"];
+
+/// 数值的显示格式，由用户设置决定
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub units: Units,
+    pub throughput: Throughput,
+    /// 小数位数
+    pub decimals: usize,
+    /// 是否使用千位分隔符
+    pub thousands_separator: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            units: Units::default(),
+            throughput: Throughput::default(),
+            decimals: 2,
+            thousands_separator: false,
+        }
+    }
+}
+
+impl NumberFormat {
+    fn group(&self, number: String) -> String {
+        if !self.thousands_separator {
+            return number;
+        }
+
+        let (sign, number) = match number.strip_prefix('-') {
+            Some(number) => ("-", number),
+            None => ("", number.as_str()),
+        };
+        let (integer, fraction) = match number.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (number, None),
+        };
+
+        // NaN / inf 等不做处理
+        if !integer.bytes().all(|byte| byte.is_ascii_digit()) {
+            return format!("{sign}{number}");
+        }
+
+        let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+
+        match fraction {
+            Some(fraction) => format!("{sign}{grouped}.{fraction}"),
+            None => format!("{sign}{grouped}"),
+        }
+    }
+
+    /// 小数，如负载、百分比
+    #[must_use]
+    pub fn number(&self, value: f64) -> String {
+        self.group(format!("{value:.*}", self.decimals))
+    }
+
+    /// 整数，如连接数、进程数
+    #[must_use]
+    pub fn integer<T: Into<i64>>(&self, value: T) -> String {
+        self.group(value.into().to_string())
+    }
+
+    fn scaled(&self, value: f64, divisor: f64, units: &[&str]) -> String {
+        // 按显示的精度取整后再比较，避免出现 1024.00 KiB
+        let rounded = |size: f64, unit_index: usize| {
+            let decimals = if unit_index == 0 { 0 } else { self.decimals };
+            format!("{size:.decimals$}").parse::<f64>().unwrap_or(size)
+        };
+
+        let mut size = value;
+        let mut unit_index = 0;
+
+        while rounded(size, unit_index).abs() >= divisor && unit_index < units.len() - 1 {
+            size /= divisor;
+            unit_index += 1;
+        }
+
+        if unit_index == 0 {
+            format!("{} {}", self.group(format!("{size:.0}")), units[0])
+        } else {
+            format!("{} {}", self.number(size), units[unit_index])
+        }
+    }
+
+    /// 容量，按设置使用 1024 (KiB) 或 1000 (kB) 进制
+    #[must_use]
+    pub fn bytes<T: Into<i64>>(&self, bytes: T) -> String {
+        let bytes = bytes.into() as f64;
+
+        match self.units {
+            Units::Binary => self.scaled(bytes, 1024.0, &BINARY_UNITS),
+            Units::Decimal => self.scaled(bytes, 1000.0, &SI_UNITS),
+        }
+    }
+
+    /// 网速，`bytes_per_sec` 为 Komari 上报的每秒字节数
+    #[must_use]
+    pub fn speed<T: Into<i64>>(&self, bytes_per_sec: T) -> String {
+        let bytes_per_sec = bytes_per_sec.into() as f64;
+
+        match self.throughput {
+            Throughput::Bits => self.scaled(bytes_per_sec * 8.0, 1000.0, &BIT_RATE_UNITS),
+            Throughput::Bytes => format!("{}/s", self.bytes(bytes_per_sec as i64)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(units: Units, throughput: Throughput) -> NumberFormat {
+        NumberFormat {
+            units,
+            throughput,
+            ..NumberFormat::default()
+        }
+    }
+
+    #[test]
+    fn bytes_binary() {
+        let fmt = format(Units::Binary, Throughput::Bits);
+        assert_eq!(fmt.bytes(0), "0 B");
+        assert_eq!(fmt.bytes(1023), "1023 B");
+        assert_eq!(fmt.bytes(1536), "1.50 KiB");
+        assert_eq!(fmt.bytes(5 * 1024 * 1024 * 1024_i64), "5.00 GiB");
+    }
+
+    #[test]
+    fn bytes_si() {
+        let fmt = format(Units::Decimal, Throughput::Bits);
+        assert_eq!(fmt.bytes(999), "999 B");
+        assert_eq!(fmt.bytes(1500), "1.50 kB");
+        assert_eq!(fmt.bytes(2_000_000_000_i64), "2.00 GB");
+    }
+
+    #[test]
+    fn rounds_up_to_next_unit() {
+        // 1023.999 KiB
+        assert_eq!(
+            format(Units::Binary, Throughput::Bits).bytes(1_048_575),
+            "1.00 MiB"
+        );
+        assert_eq!(
+            format(Units::Decimal, Throughput::Bits).bytes(999_999),
+            "1.00 MB"
+        );
+        assert_eq!(
+            format(Units::Binary, Throughput::Bytes).speed(1_048_575),
+            "1.00 MiB/s"
+        );
+    }
+
+    #[test]
+    fn speed_bits() {
+        let fmt = format(Units::Binary, Throughput::Bits);
+        assert_eq!(fmt.speed(0), "0 bps");
+        assert_eq!(fmt.speed(100), "800 bps");
+        assert_eq!(fmt.speed(125_000), "1.00 Mbps");
+        assert_eq!(fmt.speed(250_000_000), "2.00 Gbps");
+    }
+
+    #[test]
+    fn speed_bytes() {
+        assert_eq!(
+            format(Units::Binary, Throughput::Bytes).speed(1_048_576),
+            "1.00 MiB/s"
+        );
+        assert_eq!(
+            format(Units::Decimal, Throughput::Bytes).speed(1_500_000),
+            "1.50 MB/s"
+        );
+    }
+
+    #[test]
+    fn decimals() {
+        let fmt = NumberFormat {
+            decimals: 0,
+            ..NumberFormat::default()
+        };
+        assert_eq!(fmt.number(12.75), "13");
+        assert_eq!(fmt.bytes(1536), "2 KiB");
+
+        let fmt = NumberFormat {
+            decimals: 3,
+            ..NumberFormat::default()
+        };
+        assert_eq!(fmt.number(0.1234), "0.123");
+    }
+
+    #[test]
+    fn thousands_separator() {
+        let fmt = NumberFormat {
+            thousands_separator: true,
+            ..NumberFormat::default()
+        };
+        assert_eq!(fmt.number(1_234_567.891), "1,234,567.89");
+        assert_eq!(fmt.number(-1234.0), "-1,234.00");
+        assert_eq!(fmt.number(999.0), "999.00");
+        assert_eq!(fmt.integer(1_000_000), "1,000,000");
+        assert_eq!(fmt.integer(100), "100");
+        assert_eq!(fmt.number(f64::NAN), "NaN");
+
+        let fmt = NumberFormat {
+            thousands_separator: true,
+            throughput: Throughput::Bytes,
+            ..NumberFormat::default()
+        };
+        assert_eq!(fmt.bytes(1000), "1,000 B");
+        assert_eq!(fmt.speed(1000), "1,000 B/s");
+    }
+
+    #[test]
+    fn without_separator() {
+        let fmt = NumberFormat::default();
+        assert_eq!(fmt.integer(1_000_000), "1000000");
+        assert_eq!(fmt.number(1234.5678), "1234.57");
+    }
+}
@@ -1,13 +1,99 @@
-use crate::db::{DB_POOL, query_monitor_by_telegram_id};
-use crate::json_rpc::query::{AllInfo, CommonGetNodesLatestStatusSingle};
+use crate::db::{DB_POOL, insert_node_ids, query_monitor_by_telegram_id, query_node_ids};
+use crate::json_rpc::groups::node_group;
+use crate::json_rpc::query::{AllInfo, CommonGetNodesLatestStatusSingle, get_info};
+use crate::settings::Settings;
 use crate::utils::ErrorType;
 use crate::{MessageString, TelegramId};
+use sqlx::{Pool, Sqlite};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
 
 type NodeUuid = String;
-pub type SortedNodeList = Vec<(NodeUuid, CommonGetNodesLatestStatusSingle)>;
+
+/// 分配短 ID 的最大尝试次数
+const ASSIGN_ATTEMPTS: usize = 3;
+
+/// 按短 ID 排序的节点列表，元素为 `(短 ID, 节点 UUID, 最新状态)`，从未上报的节点没有最新状态
+pub type SortedNodeList = Vec<(i32, NodeUuid, Option<CommonGetNodesLatestStatusSingle>)>;
+
+/// 节点列表的显示顺序，节点 ID 不受影响
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeSort {
+    #[default]
+    Id,
+    Name,
+    /// 按 CPU 占用从高到低
+    Cpu,
+    /// 按运行时间从长到短
+    Uptime,
+}
+
+impl NodeSort {
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "id" => Some(NodeSort::Id),
+            "name" => Some(NodeSort::Name),
+            "cpu" => Some(NodeSort::Cpu),
+            "uptime" => Some(NodeSort::Uptime),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn code(self) -> &'static str {
+        match self {
+            NodeSort::Id => "id",
+            NodeSort::Name => "name",
+            NodeSort::Cpu => "cpu",
+            NodeSort::Uptime => "uptime",
+        }
+    }
+
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            NodeSort::Id => NodeSort::Name,
+            NodeSort::Name => NodeSort::Cpu,
+            NodeSort::Cpu => NodeSort::Uptime,
+            NodeSort::Uptime => NodeSort::Id,
+        }
+    }
+}
+
+/// 按 `sort` 排序节点，未上报的节点排在最后
+pub fn sort_nodes(
+    nodes: &mut [&(i32, NodeUuid, Option<CommonGetNodesLatestStatusSingle>)],
+    sort: NodeSort,
+    all_info: &AllInfo,
+) {
+    match sort {
+        NodeSort::Id => nodes.sort_by_key(|node| node.0),
+        NodeSort::Name => nodes.sort_by_cached_key(|node| {
+            let name = all_info
+                .common_nodes
+                .get(&node.1)
+                .map(|node| node.name.to_lowercase());
+            // 未知节点排在最后
+            (name.is_none(), name)
+        }),
+        NodeSort::Cpu => nodes.sort_by(|a, b| {
+            let cpu = |node: &&(i32, NodeUuid, Option<CommonGetNodesLatestStatusSingle>)| {
+                node.2.as_ref().map_or(-1.0, |status| status.cpu)
+            };
+            cpu(b).total_cmp(&cpu(a))
+        }),
+        NodeSort::Uptime => nodes.sort_by_key(|node| {
+            std::cmp::Reverse(node.2.as_ref().map_or(-1, |status| status.uptime))
+        }),
+    }
+}
 
 pub async fn get_node_id_list(
     telegram_id: TelegramId,
+    methods: &[&'static str],
+    settings: &Settings,
+    sort: NodeSort,
 ) -> Result<(MessageString, AllInfo, SortedNodeList), ErrorType> {
     let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
         error: "无法获取数据库".to_string(),
@@ -17,31 +103,178 @@ pub async fn get_node_id_list(
         return Err(ErrorType::UserNotConnected);
     };
 
-    let all_info = crate::json_rpc::query::get_all_info(&monitor.monitor_url).await?;
+    let all_info = get_info(&monitor, methods).await?;
+    let short_ids = assign_short_ids(telegram_id, &monitor.monitor_url, &all_info).await?;
 
-    let mut node_list = all_info
-        .common_nodes_latest_status
-        .iter()
-        .map(|(node_uuid, node_name)| (node_uuid.clone(), node_name.clone()))
+    // 隐藏节点仍会分配 ID，仅在列表中过滤
+    let mut node_list = node_uuids(&all_info)
+        .into_iter()
+        .filter(|node_uuid| {
+            settings.show_hidden
+                || !all_info
+                    .common_nodes
+                    .get(*node_uuid)
+                    .is_some_and(|node| node.hidden)
+        })
+        .filter_map(|node_uuid| {
+            Some((
+                *short_ids.get(node_uuid)?,
+                node_uuid.clone(),
+                all_info.common_nodes_latest_status.get(node_uuid).cloned(),
+            ))
+        })
         .collect::<Vec<_>>();
-    node_list.sort_by(|a, b| a.0.cmp(&b.0));
+    node_list.sort_by_key(|node| node.0);
 
-    let mut message_str = String::new();
+    let mut entries = node_list.iter().collect::<Vec<_>>();
+    sort_nodes(&mut entries, sort, &all_info);
+
+    let lang = settings.language;
+    // 按 ID 排序时以分组划分，未分组的节点在最后
+    let mut sections: BTreeMap<(bool, &str), String> = BTreeMap::new();
     {
-        let mut counter = 0;
+        for (counter, node_uuid, status) in entries {
+            let node = all_info.common_nodes.get(node_uuid);
+            let node_name = node.map_or_else(
+                || lang.pick("未知节点", "Unknown node").to_string(),
+                |n| n.name.clone(),
+            );
 
-        for (node_uuid, _) in node_list.clone() {
-            counter += 1;
+            let mut markers = vec![];
+            match status {
+                None => markers.push(lang.pick("从未上报", "never reported")),
+                Some(status) if !status.online => markers.push(lang.pick("离线", "offline")),
+                Some(_) => {}
+            }
+            if node.is_some_and(|node| node.hidden) {
+                markers.push(lang.pick("隐藏", "hidden"));
+            }
+            let markers = if markers.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", markers.join(", "))
+            };
 
-            let node_name = all_info
-                .common_nodes
-                .values()
-                .find(|n| n.uuid == node_uuid)
-                .map_or_else(|| "未知节点".to_string(), |n| n.name.clone());
+            let section = match sort {
+                NodeSort::Id => match node.and_then(node_group) {
+                    Some(group) => (false, group),
+                    None => (true, ""),
+                },
+                NodeSort::Name | NodeSort::Cpu | NodeSort::Uptime => (true, ""),
+            };
+            let message_str = sections.entry(section).or_default();
 
-            message_str.push_str(&format!("`{counter}` - {node_name}\n"));
+            match sort {
+                NodeSort::Id | NodeSort::Name => {
+                    message_str.push_str(&format!("`{counter}` - {node_name}{markers}\n"));
+                }
+                NodeSort::Cpu => message_str.push_str(&format!(
+                    "`{counter}` - {node_name}{markers} `{}`\n",
+                    status.as_ref().map_or_else(
+                        || String::from("-"),
+                        |status| format!("{}%", settings.number_format().number(status.cpu))
+                    )
+                )),
+                NodeSort::Uptime => message_str.push_str(&format!(
+                    "`{counter}` - {node_name}{markers} `{}`\n",
+                    status
+                        .as_ref()
+                        .filter(|status| status.uptime > 0)
+                        .map_or_else(
+                            || String::from("-"),
+                            |status| settings
+                                .format_duration(Duration::from_secs(status.uptime as u64))
+                        )
+                )),
+            }
         }
     }
 
+    let message_str = if sections.keys().all(|(ungrouped, _)| *ungrouped) {
+        sections.into_values().collect::<String>()
+    } else {
+        sections
+            .into_iter()
+            .map(|((ungrouped, group), lines)| {
+                let group = if ungrouped {
+                    lang.pick("未分组", "Ungrouped")
+                } else {
+                    group
+                };
+                format!("[{group}]\n{lines}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
     Ok((message_str, all_info, node_list))
 }
+
+/// Komari 中的所有节点，包括从未上报或不在最新状态中的节点
+fn node_uuids(all_info: &AllInfo) -> BTreeSet<&NodeUuid> {
+    all_info
+        .common_nodes
+        .keys()
+        .chain(all_info.common_nodes_latest_status.keys())
+        .collect()
+}
+
+async fn query_short_ids(
+    db: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor_url: &str,
+) -> Result<HashMap<NodeUuid, i32>, ErrorType> {
+    Ok(query_node_ids(db, telegram_id, monitor_url)
+        .await?
+        .into_iter()
+        .filter_map(|(node_uuid, short_id)| Some((node_uuid, i32::try_from(short_id).ok()?)))
+        .collect())
+}
+
+/// 读取节点的短 ID，为新节点按 Komari 的排序权重及名称分配新的 ID
+///
+/// 并发的请求可能分配到相同的 ID，冲突的记录不会写入，重新读取后为其分配下一个 ID
+async fn assign_short_ids(
+    telegram_id: TelegramId,
+    monitor_url: &str,
+    all_info: &AllInfo,
+) -> Result<HashMap<NodeUuid, i32>, ErrorType> {
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    for _ in 0..ASSIGN_ATTEMPTS {
+        let short_ids = query_short_ids(db, telegram_id, monitor_url).await?;
+
+        let mut new_nodes = node_uuids(all_info)
+            .into_iter()
+            .filter(|node_uuid| !short_ids.contains_key(*node_uuid))
+            .map(|node_uuid| {
+                let node = all_info.common_nodes.get(node_uuid);
+                (
+                    node.map_or(i64::MAX, |node| node.weight),
+                    node.map(|node| node.name.clone()).unwrap_or_default(),
+                    node_uuid.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        if new_nodes.is_empty() {
+            return Ok(short_ids);
+        }
+        new_nodes.sort();
+
+        let mut next_id = short_ids.values().max().copied().unwrap_or(0);
+        let new_ids = new_nodes
+            .into_iter()
+            .map(|(_, _, node_uuid)| {
+                next_id += 1;
+                (node_uuid, i64::from(next_id))
+            })
+            .collect::<Vec<_>>();
+
+        insert_node_ids(db, telegram_id, monitor_url, &new_ids).await?;
+    }
+
+    // 仍未分配到 ID 的节点暂不显示，下次请求时再分配
+    query_short_ids(db, telegram_id, monitor_url).await
+}
@@ -0,0 +1,113 @@
+use crate::db::{DB_POOL, query_monitor_by_telegram_id};
+use crate::json_rpc::query::{AllInfo, CommonGetNodesSingle, NODES, get_info};
+use crate::settings::Settings;
+use crate::utils::ErrorType;
+use crate::{MessageString, TelegramId};
+use std::collections::BTreeMap;
+
+/// 节点所在的分组，未设置时返回 `None`
+#[must_use]
+pub fn node_group(node: &CommonGetNodesSingle) -> Option<&str> {
+    node.group
+        .as_deref()
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+}
+
+/// 节点的标签，Komari 中以 `;` 分隔
+#[must_use]
+pub fn node_tags(node: &CommonGetNodesSingle) -> Vec<&str> {
+    node.tags
+        .as_deref()
+        .unwrap_or_default()
+        .split([';', ','])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// 仅保留 `group` 分组中的节点，分组名不区分大小写
+pub fn retain_group(all_info: &mut AllInfo, group: &str) -> Result<(), ErrorType> {
+    all_info.common_nodes.retain(|_, node| {
+        node_group(node).is_some_and(|node_group| node_group.eq_ignore_ascii_case(group))
+    });
+
+    if all_info.common_nodes.is_empty() {
+        return Err(ErrorType::GeneralError {
+            error: format!("未找到分组 {group}，使用 /group 查看所有分组"),
+        });
+    }
+
+    let common_nodes = &all_info.common_nodes;
+    all_info
+        .common_nodes_latest_status
+        .retain(|node_uuid, _| common_nodes.contains_key(node_uuid));
+
+    Ok(())
+}
+
+async fn fetch_nodes(telegram_id: TelegramId) -> Result<AllInfo, ErrorType> {
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    let Some(monitor) = query_monitor_by_telegram_id(db, telegram_id).await? else {
+        return Err(ErrorType::UserNotConnected);
+    };
+
+    get_info(&monitor, &[NODES]).await
+}
+
+fn count_list(title: &str, counts: &BTreeMap<&str, usize>) -> MessageString {
+    let mut message = format!("{title}\n\n");
+    for (name, count) in counts {
+        message.push_str(&format!("`{name}` - {count}\n"));
+    }
+    message
+}
+
+/// 列出所有分组及其节点数
+pub async fn list_groups(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
+    let all_info = fetch_nodes(telegram_id).await?;
+    let lang = settings.language;
+
+    let mut counts = BTreeMap::new();
+    for node in all_info.common_nodes.values() {
+        *counts
+            .entry(node_group(node).unwrap_or(lang.pick("未分组", "Ungrouped")))
+            .or_insert(0) += 1;
+    }
+
+    Ok(count_list(
+        lang.pick(
+            "分组 (使用 /group NAME 查看分组状态)",
+            "Groups (use /group NAME for a summary)",
+        ),
+        &counts,
+    ))
+}
+
+/// 列出所有标签及其节点数
+pub async fn list_tags(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
+    let all_info = fetch_nodes(telegram_id).await?;
+    let lang = settings.language;
+
+    let mut counts = BTreeMap::new();
+    for tag in all_info.common_nodes.values().flat_map(node_tags) {
+        *counts.entry(tag).or_insert(0) += 1;
+    }
+
+    if counts.is_empty() {
+        return Ok(lang
+            .pick("没有节点设置了标签", "No node has tags")
+            .to_string());
+    }
+
+    Ok(count_list(lang.pick("标签", "Tags"), &counts))
+}
@@ -0,0 +1,111 @@
+use crate::db::{DB_POOL, query_monitor_by_telegram_id, update_extra_headers};
+use crate::settings::{Language, Settings};
+use crate::utils::ErrorType;
+use crate::{MessageString, TelegramId};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::collections::BTreeMap;
+
+#[derive(Debug)]
+pub enum HeadersAction {
+    List,
+    Set { name: String, value: String },
+    Delete { name: String },
+    Clear,
+}
+
+/// 校验 HTTP 头，返回规范化 (小写) 的名称
+pub fn validate_header(name: &str, value: &str, lang: Language) -> Result<String, ErrorType> {
+    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| ErrorType::GeneralError {
+        error: format!(
+            "{}: {name}",
+            lang.pick("无效的 HTTP 头名称", "Invalid HTTP header name")
+        ),
+    })?;
+
+    HeaderValue::from_str(value).map_err(|_| ErrorType::GeneralError {
+        error: format!(
+            "{}: {name}",
+            lang.pick("无效的 HTTP 头内容", "Invalid HTTP header value")
+        ),
+    })?;
+
+    Ok(name.to_string())
+}
+
+fn mask_header_value(value: &str) -> String {
+    let prefix: String = value.chars().take(4).collect();
+    if prefix.chars().count() == value.chars().count() {
+        String::from("***")
+    } else {
+        format!("{prefix}***")
+    }
+}
+
+pub async fn edit_headers(
+    telegram_id: TelegramId,
+    action: HeadersAction,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
+    let lang = settings.language;
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    let Some(monitor) = query_monitor_by_telegram_id(db, telegram_id).await? else {
+        return Err(ErrorType::UserNotConnected);
+    };
+
+    let mut headers: BTreeMap<String, String> = monitor.extra_headers();
+
+    let changed = match action {
+        HeadersAction::List => false,
+        HeadersAction::Set { name, value } => {
+            let name = validate_header(&name, &value, lang)?;
+            headers.insert(name, value);
+            true
+        }
+        HeadersAction::Delete { name } => {
+            if headers.remove(&name.to_ascii_lowercase()).is_none() {
+                return Err(ErrorType::GeneralError {
+                    error: format!(
+                        "{}: {name}",
+                        lang.pick("未设置 HTTP 头", "HTTP header not set")
+                    ),
+                });
+            }
+            true
+        }
+        HeadersAction::Clear => {
+            headers.clear();
+            true
+        }
+    };
+
+    if changed {
+        let extra_headers = if headers.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&headers).ok()
+        };
+        update_extra_headers(db, telegram_id, extra_headers).await?;
+    }
+
+    if headers.is_empty() {
+        return Ok(lang
+            .pick("当前未设置额外的 HTTP 头", "No extra HTTP headers are set")
+            .to_string());
+    }
+
+    let mut msg = format!(
+        "{}:\n\n",
+        lang.pick(
+            "请求 Komari 时附加的 HTTP 头",
+            "HTTP headers sent with Komari requests"
+        )
+    );
+    for (name, value) in headers {
+        msg.push_str(&format!("`{name}`: `{}`\n", mask_header_value(&value)));
+    }
+
+    Ok(msg)
+}
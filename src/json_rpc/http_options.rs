@@ -0,0 +1,131 @@
+use crate::db::{DB_POOL, HttpOptions, query_http_options, update_http_options};
+use crate::json_rpc::build_reqwest_client;
+use crate::json_rpc::instances::mask_url_password;
+use crate::settings::{Language, Settings};
+use crate::ssrf;
+use crate::utils::ErrorType;
+use crate::{MessageString, TelegramId};
+use reqwest::Url;
+
+const MAX_TIMEOUT_SECS: u64 = 120;
+
+#[derive(Debug)]
+pub enum HttpOptionsAction {
+    Show,
+    Timeout { secs: Option<u64> },
+    Proxy { proxy: Option<String> },
+    CaCert { pem: Option<String> },
+    Insecure { enabled: bool },
+    Reset,
+}
+
+#[must_use]
+pub fn describe_http_options(options: &HttpOptions, lang: Language) -> MessageString {
+    format!(
+        "TIMEOUT: `{timeout}`
+PROXY: `{proxy}`
+CA: `{ca_cert}`
+INSECURE TLS: `{insecure}`",
+        timeout = options.timeout_secs.map_or_else(
+            || lang.pick("默认", "Default").to_string(),
+            |secs| format!("{secs}s")
+        ),
+        proxy = options
+            .proxy
+            .as_deref()
+            .map_or_else(|| lang.pick("无", "None").to_string(), mask_url_password),
+        ca_cert = options.ca_cert.as_deref().map_or_else(
+            || lang.pick("无", "None").to_string(),
+            |pem| {
+                let count = pem.matches("BEGIN CERTIFICATE").count();
+                match lang {
+                    Language::Zh => format!("{count} 个证书"),
+                    Language::En => format!("{count} certificate(s)"),
+                }
+            }
+        ),
+        insecure = if options.insecure_tls {
+            lang.pick("开启 (不校验证书)", "On (certificates not verified)")
+        } else {
+            lang.pick("关闭", "Off")
+        },
+    )
+}
+
+pub async fn edit_http_options(
+    telegram_id: TelegramId,
+    action: HttpOptionsAction,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
+    let lang = settings.language;
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    let mut options = query_http_options(db, telegram_id).await?;
+
+    let changed = match action {
+        HttpOptionsAction::Show => false,
+        HttpOptionsAction::Timeout { secs } => {
+            if secs.is_some_and(|secs| secs == 0 || secs > MAX_TIMEOUT_SECS) {
+                return Err(ErrorType::GeneralError {
+                    error: match lang {
+                        Language::Zh => format!("超时时间需在 1 ~ {MAX_TIMEOUT_SECS} 秒之间"),
+                        Language::En => {
+                            format!("Timeout must be between 1 and {MAX_TIMEOUT_SECS} seconds")
+                        }
+                    },
+                });
+            }
+            options.timeout_secs = secs;
+            true
+        }
+        HttpOptionsAction::Proxy { proxy } => {
+            if let Some(proxy) = &proxy {
+                let url = Url::parse(proxy).map_err(|e| ErrorType::GeneralError {
+                    error: format!("{}: {e}", lang.pick("无效的代理", "Invalid proxy")),
+                })?;
+                if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+                    return Err(ErrorType::GeneralError {
+                        error: lang
+                            .pick(
+                                "代理仅支持 http(s):// 或 socks5(h)://",
+                                "Only http(s):// and socks5(h):// proxies are supported",
+                            )
+                            .to_string(),
+                    });
+                }
+                ssrf::check_url(&url).await?;
+            }
+            options.proxy = proxy;
+            true
+        }
+        HttpOptionsAction::CaCert { pem } => {
+            options.ca_cert = pem;
+            true
+        }
+        HttpOptionsAction::Insecure { enabled } => {
+            options.insecure_tls = enabled;
+            true
+        }
+        HttpOptionsAction::Reset => {
+            options = HttpOptions::default();
+            true
+        }
+    };
+
+    if changed {
+        // 先尝试构建客户端，确保代理与证书有效
+        build_reqwest_client(&options)?;
+        update_http_options(db, telegram_id, &options).await?;
+    }
+
+    Ok(format!(
+        "{}:\n\n{}",
+        lang.pick(
+            "请求 Komari 时使用的连接选项",
+            "Connection options used for Komari requests"
+        ),
+        describe_http_options(&options, lang)
+    ))
+}
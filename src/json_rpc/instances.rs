@@ -0,0 +1,68 @@
+use crate::db::{DB_POOL, query_monitor_by_telegram_id};
+use crate::json_rpc::http_options::describe_http_options;
+use crate::settings::Settings;
+use crate::utils::ErrorType;
+use crate::{MessageString, TelegramId};
+use reqwest::Url;
+
+/// 隐藏 URL 中 user:pass@ 的密码
+#[must_use]
+pub fn mask_url_password(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string().trim_end_matches('/').to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+pub async fn instances(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    let Some(monitor) = query_monitor_by_telegram_id(db, telegram_id).await? else {
+        return Err(ErrorType::UserNotConnected);
+    };
+
+    let headers = monitor.extra_headers();
+    let lang = settings.language;
+
+    Ok(format!(
+        "{title}
+
+NAME: `{name}`
+URL: `{url}`
+API: `{api_type}`
+HEADERS: `{headers}`
+NOTIFICATION: `{notification}`
+
+{http_options}",
+        title = lang.pick("已连接的 Komari 实例", "Connected Komari instance"),
+        name = monitor
+            .instance_name
+            .as_deref()
+            .unwrap_or(lang.pick("未命名", "Unnamed")),
+        url = mask_url_password(&monitor.monitor_url),
+        api_type = if monitor.legacy_api {
+            lang.pick("REST API (旧版)", "REST API (legacy)")
+        } else {
+            "JSON-RPC"
+        },
+        headers = if headers.is_empty() {
+            lang.pick("无", "None").to_string()
+        } else {
+            headers.keys().cloned().collect::<Vec<_>>().join(", ")
+        },
+        notification = if monitor.notification_token.is_some() {
+            lang.pick("已启用", "Enabled")
+        } else {
+            lang.pick("未启用", "Disabled")
+        },
+        http_options = describe_http_options(&monitor.http_options(), lang),
+    ))
+}
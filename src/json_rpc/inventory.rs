@@ -0,0 +1,312 @@
+use crate::json_rpc::get_node_id::{NodeSort, get_node_id_list};
+use crate::json_rpc::groups::{node_group, node_tags};
+use crate::json_rpc::query::STATUS_METHODS;
+use crate::settings::Settings;
+use crate::utils::{ErrorType, MAX_PAGE_CHARS, escaped_len};
+use crate::{MessageString, TelegramId};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 导出文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// 导出文件中的一行，包含节点的静态信息与最新状态
+///
+/// 数值保持原始单位 (字节、字节每秒、秒)，便于表格软件处理
+#[derive(Debug, Clone, Serialize)]
+struct ExportRow {
+    id: i32,
+    uuid: String,
+    name: String,
+    group: String,
+    tags: String,
+    region: String,
+    os: String,
+    kernel_version: String,
+    arch: String,
+    virtualization: String,
+    cpu_name: String,
+    cpu_cores: i64,
+    gpu_name: String,
+    mem_total: i64,
+    swap_total: i64,
+    disk_total: i64,
+    hidden: bool,
+    online: bool,
+    client: Option<String>,
+    updated_at: Option<String>,
+    cpu: Option<f64>,
+    ram: Option<i64>,
+    disk: Option<i64>,
+    load: Option<f64>,
+    net_in: Option<i64>,
+    net_out: Option<i64>,
+    net_total_up: Option<i64>,
+    net_total_down: Option<i64>,
+    connections: Option<i64>,
+    connections_udp: Option<i64>,
+    uptime: Option<i64>,
+}
+
+/// 清单中统计的字段
+type InventoryField = fn(&ExportRow) -> &str;
+
+const CSV_HEADER: [&str; 31] = [
+    "id",
+    "uuid",
+    "name",
+    "group",
+    "tags",
+    "region",
+    "os",
+    "kernel_version",
+    "arch",
+    "virtualization",
+    "cpu_name",
+    "cpu_cores",
+    "gpu_name",
+    "mem_total",
+    "swap_total",
+    "disk_total",
+    "hidden",
+    "online",
+    "client",
+    "updated_at",
+    "cpu",
+    "ram",
+    "disk",
+    "load",
+    "net_in",
+    "net_out",
+    "net_total_up",
+    "net_total_down",
+    "connections",
+    "connections_udp",
+    "uptime",
+];
+
+impl ExportRow {
+    fn csv_record(&self) -> [String; CSV_HEADER.len()] {
+        fn opt<T: ToString>(value: Option<&T>) -> String {
+            value.map(ToString::to_string).unwrap_or_default()
+        }
+
+        [
+            self.id.to_string(),
+            self.uuid.clone(),
+            self.name.clone(),
+            self.group.clone(),
+            self.tags.clone(),
+            self.region.clone(),
+            self.os.clone(),
+            self.kernel_version.clone(),
+            self.arch.clone(),
+            self.virtualization.clone(),
+            self.cpu_name.clone(),
+            self.cpu_cores.to_string(),
+            self.gpu_name.clone(),
+            self.mem_total.to_string(),
+            self.swap_total.to_string(),
+            self.disk_total.to_string(),
+            self.hidden.to_string(),
+            self.online.to_string(),
+            opt(self.client.as_ref()),
+            opt(self.updated_at.as_ref()),
+            opt(self.cpu.as_ref()),
+            opt(self.ram.as_ref()),
+            opt(self.disk.as_ref()),
+            opt(self.load.as_ref()),
+            opt(self.net_in.as_ref()),
+            opt(self.net_out.as_ref()),
+            opt(self.net_total_up.as_ref()),
+            opt(self.net_total_down.as_ref()),
+            opt(self.connections.as_ref()),
+            opt(self.connections_udp.as_ref()),
+            opt(self.uptime.as_ref()),
+        ]
+    }
+}
+
+/// 按 RFC 4180 转义 CSV 字段
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+async fn export_rows(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<(String, Vec<ExportRow>), ErrorType> {
+    let (_, all_info, node_list) =
+        get_node_id_list(telegram_id, &STATUS_METHODS, settings, NodeSort::Id).await?;
+
+    let rows = node_list
+        .iter()
+        .filter_map(|(node_id, node_uuid, status)| {
+            let node = all_info.common_nodes.get(node_uuid)?;
+            Some(ExportRow {
+                id: *node_id,
+                uuid: node_uuid.clone(),
+                name: node.name.clone(),
+                group: node_group(node).unwrap_or_default().to_string(),
+                tags: node_tags(node).join(";"),
+                region: node.region.clone(),
+                os: node.os.clone(),
+                kernel_version: node.kernel_version.clone(),
+                arch: node.arch.clone(),
+                virtualization: node.virtualization.clone(),
+                cpu_name: node.cpu_name.clone(),
+                cpu_cores: node.cpu_cores,
+                gpu_name: node.gpu_name.clone(),
+                mem_total: node.mem_total,
+                swap_total: node.swap_total,
+                disk_total: node.disk_total,
+                hidden: node.hidden,
+                online: status.as_ref().is_some_and(|status| status.online),
+                client: status.as_ref().map(|status| status.client.clone()),
+                updated_at: status.as_ref().map(|status| status.time.clone()),
+                cpu: status.as_ref().map(|status| status.cpu),
+                ram: status.as_ref().map(|status| status.ram),
+                disk: status.as_ref().map(|status| status.disk),
+                load: status.as_ref().map(|status| status.load),
+                net_in: status.as_ref().map(|status| status.net_in),
+                net_out: status.as_ref().map(|status| status.net_out),
+                net_total_up: status.as_ref().map(|status| status.net_total_up),
+                net_total_down: status.as_ref().map(|status| status.net_total_down),
+                connections: status.as_ref().map(|status| status.connections),
+                connections_udp: status.as_ref().map(|status| status.connections_udp),
+                uptime: status.as_ref().map(|status| status.uptime),
+            })
+        })
+        .collect();
+
+    Ok((all_info.common_public_info.sitename, rows))
+}
+
+/// 按操作系统、内核、架构、虚拟化、CPU、地区及客户端版本统计节点数
+///
+/// 内容超出单条消息时拆分为多页，每页单独发送
+pub async fn inventory(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<Vec<MessageString>, ErrorType> {
+    let (sitename, rows) = export_rows(telegram_id, settings).await?;
+    let lang = settings.language;
+    let unknown = lang.pick("未知", "Unknown");
+
+    let sections: [(&str, InventoryField); 7] = [
+        ("OS", |row| &row.os),
+        ("KERNEL", |row| &row.kernel_version),
+        ("ARCH", |row| &row.arch),
+        ("VIRTUALIZATION", |row| &row.virtualization),
+        ("CPU", |row| &row.cpu_name),
+        ("REGION", |row| &row.region),
+        ("AGENT", |row| row.client.as_deref().unwrap_or_default()),
+    ];
+
+    let mut pages = vec![];
+    let mut page = format!("{}: `{}`\n", lang.pick("节点数", "Nodes"), rows.len());
+    for (title, field) in sections {
+        let mut counts = HashMap::new();
+        for row in &rows {
+            let value = field(row).trim();
+            *counts
+                .entry(if value.is_empty() { unknown } else { value })
+                .or_insert(0) += 1;
+        }
+
+        // 数量多的在前，数量相同时按名称排序
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let mut heading = format!("\n{title}\n");
+        for (value, count) in counts {
+            let line = format!("`{}` - {count}\n", value.replace('`', "'"));
+            if !page.is_empty()
+                && escaped_len(&page) + escaped_len(&heading) + escaped_len(&line) > MAX_PAGE_CHARS
+            {
+                pages.push(std::mem::take(&mut page));
+                // 跨页的分段在新页中重复标题
+                heading = format!("{title}\n");
+            }
+            page.push_str(&heading);
+            page.push_str(&line);
+            heading.clear();
+        }
+    }
+    pages.push(page);
+
+    let title = format!("{sitename} {}", lang.pick("节点清单", "inventory"));
+    let total = pages.len();
+    Ok(pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, page)| {
+            if total > 1 {
+                format!("{title} ({}/{total})\n{page}", index + 1)
+            } else {
+                format!("{title}\n{page}")
+            }
+        })
+        .collect())
+}
+
+/// 导出所有节点，返回文件名与文件内容
+pub async fn export(
+    telegram_id: TelegramId,
+    format: ExportFormat,
+    settings: &Settings,
+) -> Result<(String, Vec<u8>), ErrorType> {
+    let (_, rows) = export_rows(telegram_id, settings).await?;
+
+    let content = match format {
+        ExportFormat::Csv => {
+            let mut content = CSV_HEADER.join(",");
+            content.push_str("\r\n");
+            for row in &rows {
+                let record = row.csv_record().map(|field| csv_field(&field));
+                content.push_str(&record.join(","));
+                content.push_str("\r\n");
+            }
+            content.into_bytes()
+        }
+        ExportFormat::Json => {
+            serde_json::to_vec_pretty(&rows).map_err(|e| ErrorType::GeneralError {
+                error: format!("无法序列化节点数据: {e}"),
+            })?
+        }
+    };
+
+    let file_name = format!(
+        "komari-nodes-{}.{}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        format.extension()
+    );
+
+    Ok((file_name, content))
+}
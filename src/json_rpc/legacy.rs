@@ -0,0 +1,267 @@
+use crate::db::Monitor;
+use crate::json_rpc::cache::CachedBatch;
+use crate::json_rpc::query::{
+    CommonGetNodes, CommonGetNodesLatestStatus, CommonGetNodesLatestStatusSingle,
+    CommonGetNodesSingle, CommonGetPublicInfo, CommonGetVersion, NODES, NODES_LATEST_STATUS,
+    PUBLIC_INFO, VERSION,
+};
+use crate::json_rpc::reqwest_client_for;
+use crate::json_rpc::resilience::{RequestFailure, with_retry};
+use crate::settings::elapsed_since;
+use crate::utils::ErrorType;
+use log::debug;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// 同时获取节点最近记录的最大请求数
+const MAX_CONCURRENT_RECENT: usize = 8;
+
+/// 旧版 API 不返回在线状态，最近一条记录在此时长内即视为在线
+const ONLINE_WINDOW: Duration = Duration::from_secs(60);
+
+// 旧版 Komari REST API 的统一响应格式
+#[derive(Debug, Deserialize)]
+struct LegacyResponse<T> {
+    status: String,
+    #[serde(default)]
+    message: String,
+    data: Option<T>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+struct LegacyRecord {
+    cpu: LegacyUsage,
+    gpu: LegacyUsage,
+    ram: LegacyUsed,
+    swap: LegacyUsed,
+    load: LegacyLoad,
+    disk: LegacyUsed,
+    network: LegacyNetwork,
+    connections: LegacyConnections,
+    process: i64,
+    temp: i64,
+    uptime: i64,
+    updated_at: String,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+struct LegacyUsage {
+    usage: f64,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+struct LegacyUsed {
+    total: i64,
+    used: i64,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+struct LegacyLoad {
+    load1: f64,
+    load5: f64,
+    load15: f64,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+struct LegacyNetwork {
+    up: i64,
+    down: i64,
+    #[serde(rename = "totalUp")]
+    total_up: i64,
+    #[serde(rename = "totalDown")]
+    total_down: i64,
+}
+
+#[derive(Default, Debug, Clone, Deserialize)]
+#[serde(default)]
+struct LegacyConnections {
+    tcp: i64,
+    udp: i64,
+}
+
+impl From<LegacyRecord> for CommonGetNodesLatestStatusSingle {
+    fn from(record: LegacyRecord) -> Self {
+        // 无法解析更新时间时视为离线
+        let online =
+            elapsed_since(&record.updated_at).is_some_and(|elapsed| elapsed < ONLINE_WINDOW);
+
+        Self {
+            time: record.updated_at,
+            cpu: record.cpu.usage,
+            gpu: record.gpu.usage,
+            ram: record.ram.used,
+            ram_total: record.ram.total,
+            swap: record.swap.used,
+            swap_total: record.swap.total,
+            load: record.load.load1,
+            load5: record.load.load5,
+            load15: record.load.load15,
+            temp: record.temp,
+            disk: record.disk.used,
+            disk_total: record.disk.total,
+            net_in: record.network.down,
+            net_out: record.network.up,
+            net_total_up: record.network.total_up,
+            net_total_down: record.network.total_down,
+            process: record.process,
+            connections: record.connections.tcp,
+            connections_udp: record.connections.udp,
+            uptime: record.uptime,
+            online,
+            ..Default::default()
+        }
+    }
+}
+
+async fn get<T: DeserializeOwned>(monitor: &Monitor, path: &str) -> Result<T, ErrorType> {
+    get_with_breaker(monitor, path, &monitor.monitor_url).await
+}
+
+/// 请求 `path`，失败计入 `breaker_key` 对应的熔断器
+async fn get_with_breaker<T: DeserializeOwned>(
+    monitor: &Monitor,
+    path: &str,
+    breaker_key: &str,
+) -> Result<T, ErrorType> {
+    let client = reqwest_client_for(monitor).await?;
+
+    let url = format!("{}{path}", monitor.monitor_url);
+    let headers = monitor.extra_headers();
+
+    let response = with_retry(breaker_key, || async {
+        let mut request = client.get(&url);
+        for (name, value) in &headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)?;
+
+        response.json::<LegacyResponse<T>>().await.map_err(|e| {
+            RequestFailure::Fatal(ErrorType::JsonParseError {
+                error: format!("Json 解析错误: {path}: {e}"),
+            })
+        })
+    })
+    .await?;
+
+    if response.status != "success" {
+        return Err(ErrorType::GeneralError {
+            error: format!("{path} 返回错误: {}", response.message),
+        });
+    }
+
+    response.data.ok_or_else(|| ErrorType::JsonParseError {
+        error: format!("Json 解析错误: {path} 缺少 data 字段"),
+    })
+}
+
+/// 检查实例是否提供旧版 REST API
+pub async fn probe(monitor: &Monitor) -> Result<(), ErrorType> {
+    get::<CommonGetPublicInfo>(monitor, "/api/public")
+        .await
+        .map(|_| ())
+}
+
+async fn get_nodes(monitor: &Monitor) -> Result<CommonGetNodes, ErrorType> {
+    let nodes = get::<Vec<CommonGetNodesSingle>>(monitor, "/api/nodes").await?;
+
+    Ok(nodes
+        .into_iter()
+        .map(|node| (node.uuid.clone(), node))
+        .collect())
+}
+
+async fn get_latest_status(
+    monitor: &Monitor,
+    nodes: &CommonGetNodes,
+) -> Result<CommonGetNodesLatestStatus, ErrorType> {
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_RECENT));
+    let mut tasks = JoinSet::new();
+    for uuid in nodes.keys() {
+        let monitor = monitor.clone();
+        let uuid = uuid.clone();
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+
+            // 单个节点的失败按节点熔断，不影响整个实例
+            let path = format!("/api/recent/{uuid}");
+            let breaker_key = format!("{}{path}", monitor.monitor_url);
+            let records =
+                get_with_breaker::<Vec<LegacyRecord>>(&monitor, &path, &breaker_key).await;
+            (uuid, records)
+        });
+    }
+
+    let mut latest_status = CommonGetNodesLatestStatus::new();
+    while let Some(result) = tasks.join_next().await {
+        let Ok((uuid, records)) = result else {
+            continue;
+        };
+
+        // 旧版 API 仅保留最近的记录，没有记录的节点视为未上报
+        match records {
+            Ok(records) => {
+                if let Some(record) = records.into_iter().last() {
+                    latest_status.insert(uuid, record.into());
+                }
+            }
+            Err(e) => debug!("获取节点 {uuid} 的最近记录失败: {e}"),
+        }
+    }
+
+    Ok(latest_status)
+}
+
+/// 通过旧版 REST API 填充缓存中缺失的方法
+pub async fn fetch_missing(
+    monitor: &Monitor,
+    cached: &mut CachedBatch,
+    missing: &[&'static str],
+) -> Result<(), ErrorType> {
+    if missing.contains(&PUBLIC_INFO) {
+        cached.store(
+            PUBLIC_INFO,
+            get::<CommonGetPublicInfo>(monitor, "/api/public").await?,
+        );
+    }
+
+    if missing.contains(&VERSION) {
+        let version = get::<CommonGetVersion>(monitor, "/api/version")
+            .await
+            .unwrap_or_default();
+        cached.store(VERSION, version);
+    }
+
+    if missing.contains(&NODES) || missing.contains(&NODES_LATEST_STATUS) {
+        let nodes = match cached.get::<CommonGetNodes>(NODES) {
+            Some(nodes) => nodes,
+            None => get_nodes(monitor).await?,
+        };
+
+        if missing.contains(&NODES_LATEST_STATUS) {
+            cached.store(
+                NODES_LATEST_STATUS,
+                get_latest_status(monitor, &nodes).await?,
+            );
+        }
+
+        if missing.contains(&NODES) {
+            cached.store(NODES, nodes);
+        }
+    }
+
+    Ok(())
+}
@@ -1,23 +1,48 @@
 pub mod all_komari_info;
+pub mod cache;
+pub mod client;
+pub mod compare;
 pub mod connect;
+pub mod format;
 pub mod get_node_id;
+pub mod groups;
+pub mod headers;
+pub mod http_options;
+pub mod instances;
+pub mod inventory;
+pub mod legacy;
+pub mod outdated;
 pub mod query;
+pub mod resilience;
+pub mod search;
 pub mod status;
+pub mod top;
 pub mod total_status;
+pub mod uptime;
 
+use crate::db::{HttpOptions, Monitor};
+use crate::ssrf;
 use crate::utils::ErrorType;
-use reqwest::Client;
+use reqwest::{Certificate, Client, ClientBuilder, Proxy, Url};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
 use tokio::sync::OnceCell;
 
 pub static REQWEST_CLIENT: OnceCell<reqwest::Client> = OnceCell::const_new();
 
+// 按不同的连接选项复用客户端
+static CLIENT_POOL: LazyLock<Mutex<HashMap<HttpOptions, Client>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+
 pub async fn create_reqwest_client() -> Result<&'static Client, ErrorType> {
     REQWEST_CLIENT
         .get_or_try_init(|| async {
             let client_build = reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(5))
+                .timeout(std::time::Duration::from_secs(DEFAULT_TIMEOUT_SECS))
                 .user_agent("komari-tgbot-rs");
-            client_build.build()
+            guard_client(client_build).build()
         })
         .await
         .map_err(|e| ErrorType::UnableToCreateReqwestClient {
@@ -25,26 +50,80 @@ pub async fn create_reqwest_client() -> Result<&'static Client, ErrorType> {
         })
 }
 
-pub fn bytes_to_pretty_string<T: Into<i64>>(bytes: T) -> String {
-    let bytes = bytes.into() as f64;
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
-    const DIVISOR: f64 = 1024.0;
+/// 根据实例的连接选项获取客户端，未设置任何选项时使用全局客户端
+pub async fn reqwest_client_for(monitor: &Monitor) -> Result<Client, ErrorType> {
+    // 直接使用 IP 的地址不经过 DNS 解析，需单独检查
+    if let Ok(url) = Url::parse(&monitor.monitor_url) {
+        ssrf::check_ip_literal(&url)?;
+    }
+
+    let options = monitor.http_options();
+
+    if options == HttpOptions::default() {
+        return create_reqwest_client().await.cloned();
+    }
 
-    if bytes == 0.0 {
-        return "0 B".to_string();
+    if let Some(client) = CLIENT_POOL
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(&options)
+    {
+        return Ok(client.clone());
     }
 
-    let mut size = bytes;
-    let mut unit_index = 0;
+    let client = build_reqwest_client(&options)?;
+
+    CLIENT_POOL
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(options, client.clone());
+
+    Ok(client)
+}
+
+pub fn build_reqwest_client(options: &HttpOptions) -> Result<Client, ErrorType> {
+    let mut client_build = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(
+            options.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        ))
+        .user_agent("komari-tgbot-rs");
 
-    while size >= DIVISOR && unit_index < UNITS.len() - 1 {
-        size /= DIVISOR;
-        unit_index += 1;
+    if let Some(proxy) = &options.proxy {
+        let proxy = Proxy::all(proxy).map_err(|e| ErrorType::UnableToCreateReqwestClient {
+            error: format!("无效的代理: {e}"),
+        })?;
+        client_build = client_build.proxy(proxy);
     }
 
-    if unit_index == 0 {
-        format!("{} {}", size as i64, UNITS[unit_index])
+    if let Some(ca_cert) = &options.ca_cert {
+        let certificates = Certificate::from_pem_bundle(ca_cert.as_bytes()).map_err(|e| {
+            ErrorType::UnableToCreateReqwestClient {
+                error: format!("无效的 CA 证书: {e}"),
+            }
+        })?;
+        for certificate in certificates {
+            client_build = client_build.add_root_certificate(certificate);
+        }
+    }
+
+    if options.insecure_tls {
+        client_build = client_build.danger_accept_invalid_certs(true);
+    }
+
+    guard_client(client_build)
+        .build()
+        .map_err(|e| ErrorType::UnableToCreateReqwestClient {
+            error: e.to_string(),
+        })
+}
+
+/// 启用 SSRF 防护时，在解析与重定向阶段过滤内网地址
+fn guard_client(client_build: ClientBuilder) -> ClientBuilder {
+    if ssrf::enabled() {
+        client_build
+            .dns_resolver(Arc::new(ssrf::GuardedResolver))
+            .redirect(ssrf::redirect_policy())
     } else {
-        format!("{:.2} {}", size, UNITS[unit_index])
+        client_build
     }
 }
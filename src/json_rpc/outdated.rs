@@ -0,0 +1,238 @@
+use crate::db::{
+    DB_POOL, NodeSystemRow, query_monitor_by_telegram_id, query_node_systems, upsert_node_systems,
+};
+use crate::json_rpc::get_node_id::{NodeSort, get_node_id_list};
+use crate::json_rpc::query::{CommonGetNodes, STATUS_METHODS};
+use crate::settings::Settings;
+use crate::utils::ErrorType;
+use crate::{MessageString, TelegramId};
+use chrono::Utc;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// 报告中显示系统变更的时长，与每周报告的间隔一致
+pub const SYSTEM_CHANGE_WINDOW: Duration = Duration::from_secs(7 * 86400);
+
+static VERSION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)*").unwrap());
+
+/// 与上次记录的版本比较，返回每个节点当前的记录
+fn track_systems(
+    previous: &[NodeSystemRow],
+    nodes: &CommonGetNodes,
+    now: i64,
+) -> Vec<NodeSystemRow> {
+    let previous = previous
+        .iter()
+        .map(|row| (row.node_uuid.as_str(), row))
+        .collect::<HashMap<_, _>>();
+
+    nodes
+        .iter()
+        // 尚未上报系统信息的节点无法判断
+        .filter(|(_, node)| !(node.os.is_empty() && node.kernel_version.is_empty()))
+        .map(|(node_uuid, node)| match previous.get(node_uuid.as_str()) {
+            Some(tracked)
+                if tracked.os != node.os || tracked.kernel_version != node.kernel_version =>
+            {
+                NodeSystemRow {
+                    node_uuid: node_uuid.clone(),
+                    os: node.os.clone(),
+                    kernel_version: node.kernel_version.clone(),
+                    old_os: Some(tracked.os.clone()),
+                    old_kernel_version: Some(tracked.kernel_version.clone()),
+                    changed_at: Some(now),
+                }
+            }
+            Some(tracked) => (*tracked).clone(),
+            None => NodeSystemRow {
+                node_uuid: node_uuid.clone(),
+                os: node.os.clone(),
+                kernel_version: node.kernel_version.clone(),
+                old_os: None,
+                old_kernel_version: None,
+                changed_at: None,
+            },
+        })
+        .collect()
+}
+
+/// 记录本次获取到的系统与内核版本，与上次不同即视为发生变更，返回近期发生了变更的节点
+///
+/// 记录保存在数据库中，重启 Bot 后仍可与上次的版本比较
+async fn record_systems(
+    telegram_id: TelegramId,
+    nodes: &CommonGetNodes,
+    now: i64,
+) -> Result<HashMap<String, NodeSystemRow>, ErrorType> {
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    let Some(monitor) = query_monitor_by_telegram_id(db, telegram_id).await? else {
+        return Err(ErrorType::UserNotConnected);
+    };
+
+    let previous = query_node_systems(db, telegram_id, &monitor.monitor_url).await?;
+    let current = track_systems(&previous, nodes, now);
+
+    let updated = current
+        .iter()
+        .filter(|row| !previous.contains(row))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !updated.is_empty() {
+        upsert_node_systems(db, telegram_id, &monitor.monitor_url, &updated).await?;
+    }
+
+    let window = SYSTEM_CHANGE_WINDOW.as_secs() as i64;
+    Ok(current
+        .into_iter()
+        .filter(|row| row.changed_at.is_some_and(|at| now - at < window))
+        .map(|row| (row.node_uuid.clone(), row))
+        .collect())
+}
+
+/// 提取版本号中的数字部分，如 `v1.0.5-beta` 为 `[1, 0, 5]`
+#[must_use]
+pub fn parse_version(version: &str) -> Option<Vec<u64>> {
+    VERSION_REGEX
+        .find(version)?
+        .as_str()
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// 比较两个版本号，无法解析时视为不可比较
+fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    Some(parse_version(a)?.cmp(&parse_version(b)?))
+}
+
+/// 出现次数最多的版本，次数相同时取较新的版本
+fn most_common_version<'a>(versions: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut counts = HashMap::new();
+    for version in versions {
+        *counts.entry(version).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by(|a, b| {
+            a.1.cmp(&b.1)
+                .then_with(|| compare_versions(a.0, b.0).unwrap_or(Ordering::Equal))
+                .then_with(|| b.0.cmp(a.0))
+        })
+        .map(|(version, _)| version)
+}
+
+/// 客户端版本落后于目标版本或最常见版本的节点，以及近期更换了系统或内核的节点
+///
+/// 每次生成报告都会重新获取节点并记录系统与内核版本，每周报告同样如此
+pub async fn outdated_report(
+    telegram_id: TelegramId,
+    settings: &Settings,
+) -> Result<MessageString, ErrorType> {
+    let (_, all_info, node_list) =
+        get_node_id_list(telegram_id, &STATUS_METHODS, settings, NodeSort::Id).await?;
+    let lang = settings.language;
+    let now = Utc::now().timestamp();
+    let mut systems = record_systems(telegram_id, &all_info.common_nodes, now).await?;
+
+    let agents = node_list
+        .iter()
+        .filter_map(|(node_id, node_uuid, status)| {
+            let node = all_info.common_nodes.get(node_uuid)?;
+            let client = status.as_ref()?.client.trim();
+            (!client.is_empty()).then_some((*node_id, node.name.as_str(), client))
+        })
+        .collect::<Vec<_>>();
+
+    let (reference, reference_label) = match settings.agent_target.as_deref() {
+        Some(target) => (Some(target), lang.pick("目标版本", "target version")),
+        None => (
+            most_common_version(agents.iter().map(|(_, _, client)| *client)),
+            lang.pick("最常见的版本", "most common version"),
+        ),
+    };
+
+    let mut message = format!(
+        "{} {}\n\n",
+        all_info.common_public_info.sitename,
+        lang.pick("版本报告", "version report")
+    );
+
+    match reference {
+        None => message.push_str(lang.pick(
+            "没有节点上报客户端版本\n",
+            "No node reported an agent version\n",
+        )),
+        Some(reference) => {
+            message.push_str(&format!("AGENT: `{reference}` ({reference_label})\n"));
+
+            // 无法解析的版本号与基准不同时也列出
+            let outdated = agents
+                .iter()
+                .filter(|(_, _, client)| match compare_versions(client, reference) {
+                    Some(ordering) => ordering == Ordering::Less,
+                    None => *client != reference,
+                })
+                .collect::<Vec<_>>();
+
+            if outdated.is_empty() {
+                message.push_str(
+                    lang.pick("所有节点的客户端均为最新\n", "All agents are up to date\n"),
+                );
+            } else {
+                message.push_str(&format!(
+                    "{}: {}\n",
+                    lang.pick("落后的节点", "Outdated nodes"),
+                    outdated.len()
+                ));
+                for (node_id, name, client) in outdated {
+                    message.push_str(&format!("`{node_id}` {name} - `{client}`\n"));
+                }
+            }
+        }
+    }
+
+    let changes = node_list
+        .iter()
+        .filter_map(|(node_id, node_uuid, _)| {
+            let node = all_info.common_nodes.get(node_uuid)?;
+            Some((*node_id, node, systems.remove(node_uuid)?))
+        })
+        .collect::<Vec<_>>();
+
+    if !changes.is_empty() {
+        message.push_str(&format!(
+            "\n{}:\n",
+            lang.pick("近期系统变更", "Recent OS / kernel changes")
+        ));
+        for (node_id, node, change) in changes {
+            let old_os = change.old_os.unwrap_or_default();
+            let old_kernel_version = change.old_kernel_version.unwrap_or_default();
+            let elapsed = now - change.changed_at.unwrap_or(now);
+
+            let mut line = format!("`{node_id}` {}:", node.name);
+            if old_os != node.os {
+                line.push_str(&format!(" `{old_os}` -> `{}`", node.os));
+            }
+            if old_kernel_version != node.kernel_version {
+                line.push_str(&format!(
+                    " `{old_kernel_version}` -> `{}`",
+                    node.kernel_version
+                ));
+            }
+            line.push_str(&format!(
+                " ({})\n",
+                settings.format_ago(Duration::from_secs(elapsed.max(0) as u64))
+            ));
+            message.push_str(&line);
+        }
+    }
+
+    Ok(message)
+}
@@ -1,38 +1,32 @@
-use crate::json_rpc::create_reqwest_client;
-use crate::utils::{ErrorString, ErrorType};
+use crate::db::Monitor;
+use crate::json_rpc::client::{JsonRpcClient, METHOD_NOT_FOUND};
+use crate::json_rpc::{cache, legacy, uptime};
+use crate::utils::ErrorType;
+use log::debug;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use std::collections::HashMap;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct JsonRpcRequestBase {
-    pub jsonrpc: String,
-    pub method: String,
-    pub id: i64,
-}
+pub const RPC_HELP: &str = "rpc.help";
+pub const RPC_METHODS: &str = "rpc.methods";
+pub const RPC_PING: &str = "rpc.ping";
+pub const RPC_VERSION: &str = "rpc.version";
+pub const PUBLIC_INFO: &str = "common:getPublicInfo";
+pub const NODES: &str = "common:getNodes";
+pub const NODES_LATEST_STATUS: &str = "common:getNodesLatestStatus";
+pub const ME: &str = "common:getMe";
+pub const VERSION: &str = "common:getVersion";
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct JsonRpcResponseBase {
-    pub jsonrpc: String,
-    pub id: i64,
-    pub result: Value,
-}
+/// 连接时展示站点信息所需的方法
+pub const CONNECT_METHODS: [&str; 3] = [PUBLIC_INFO, NODES, VERSION];
+/// 渲染节点状态所需的方法
+pub const STATUS_METHODS: [&str; 3] = [PUBLIC_INFO, NODES, NODES_LATEST_STATUS];
+/// 渲染节点列表所需的方法
+pub const NODE_LIST_METHODS: [&str; 2] = [NODES, NODES_LATEST_STATUS];
 
-const JSON_RPC_VERSION: &str = "2.0";
-const JSON_RPC_METHOD: [&str; 9] = [
-    "rpc.help",
-    "rpc.methods",
-    "rpc.ping",
-    "rpc.version",
-    "common:getPublicInfo",
-    "common:getNodes",
-    "common:getNodesLatestStatus",
-    "common:getMe",
-    "common:getVersion",
-];
+/// 旧版本 Komari 可能不支持的方法，请求失败时使用默认值
+const OPTIONAL_METHODS: [&str; 6] = [RPC_HELP, RPC_METHODS, RPC_PING, RPC_VERSION, ME, VERSION];
 
+/// 一次或多次请求的结果，未请求的字段保持默认值
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AllInfo {
     pub rpc_help: RpcHelp,
@@ -46,175 +40,133 @@ pub struct AllInfo {
     pub common_version: CommonGetVersion,
 }
 
-pub async fn get_all_info(http_url: &str) -> Result<AllInfo, ErrorType> {
-    let client = create_reqwest_client().await?;
-
-    let url = format!("{http_url}/api/rpc2");
-
-    let json_rpc_post_body = JSON_RPC_METHOD
-        .iter()
-        .enumerate()
-        .map(|(index, method)| JsonRpcRequestBase {
-            jsonrpc: JSON_RPC_VERSION.to_string(),
-            method: (*method).to_string(),
-            id: (index + 1) as i64,
-        })
-        .collect::<Vec<_>>();
+/// 获取 `methods` 对应的信息，优先使用缓存
+pub async fn get_info(monitor: &Monitor, methods: &[&'static str]) -> Result<AllInfo, ErrorType> {
+    fetch_info(monitor, methods, false).await
+}
 
-    let response = client
-        .post(&url)
-        .json(&json_rpc_post_body)
-        .send()
-        .await
-        .map_err(|e| ErrorType::RequestError {
-            error: e.to_string(),
-        })?;
+/// 跳过缓存，直接从 Komari 获取 `methods` 对应的信息
+pub async fn get_info_uncached(
+    monitor: &Monitor,
+    methods: &[&'static str],
+) -> Result<AllInfo, ErrorType> {
+    fetch_info(monitor, methods, true).await
+}
 
-    let json_rpc_response_body =
-        response
-            .json::<Vec<JsonRpcResponseBase>>()
-            .await
-            .map_err(|e| ErrorType::RequestError {
-                error: e.to_string(),
-            })?;
+async fn fetch_info(
+    monitor: &Monitor,
+    methods: &[&'static str],
+    force: bool,
+) -> Result<AllInfo, ErrorType> {
+    let mut cached = cache::lock(&monitor.cache_key(), methods, force).await;
 
-    let rpc_help: RpcHelp = serde_json::from_value(
-        json_rpc_response_body
+    let mut missing = cached.missing();
+    if monitor.legacy_api {
+        legacy::fetch_missing(monitor, &mut cached, &missing).await?;
+        missing.clear();
+    } else if let Some(supported) = supported_methods(monitor) {
+        // 不支持的可选方法保持默认值，不再请求
+        let unsupported = missing
+            .extract_if(.., |method| !supported.iter().any(|m| m == method))
+            .collect::<Vec<_>>();
+        if let Some(method) = unsupported
             .iter()
-            .find(|response| response.id == 1)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 1 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 1 的响应: {e}"),
-    })?;
+            .find(|method| !OPTIONAL_METHODS.contains(method))
+        {
+            return Err(ErrorType::JsonRpcError {
+                method: (*method).to_string(),
+                code: METHOD_NOT_FOUND,
+                message: String::from("Method not found"),
+                data: None,
+            });
+        }
+    }
 
-    let rpc_methods: RpcMethods = serde_json::from_value(
-        json_rpc_response_body
+    if missing == [NODES_LATEST_STATUS] {
+        // 刷新状态时通常只有最新状态过期，无需批量请求
+        let client = JsonRpcClient::new(monitor);
+        let latest_status = client
+            .call::<CommonGetNodesLatestStatus>(NODES_LATEST_STATUS, None)
+            .await?;
+        cached.store(NODES_LATEST_STATUS, latest_status);
+    } else if !missing.is_empty() {
+        let client = JsonRpcClient::new(monitor);
+        let mut batch = client.batch();
+        let ids = missing
             .iter()
-            .find(|response| response.id == 2)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 2 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 2 的响应: {e}"),
-    })?;
+            .map(|method| (*method, batch.add(method, None)))
+            .collect::<Vec<_>>();
 
-    let rpc_ping: RpcPing = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 3)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 3 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 3 的响应: {e}"),
-    })?;
+        let mut response = batch.send().await?;
 
-    let rpc_version: RpcVersion = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 4)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 4 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 4 的响应: {e}"),
-    })?;
+        for (method, id) in ids {
+            match method {
+                RPC_HELP => cached.store(method, optional(method, response.take::<RpcHelp>(id))?),
+                RPC_METHODS => {
+                    cached.store(method, optional(method, response.take::<RpcMethods>(id))?)
+                }
+                RPC_PING => cached.store(method, optional(method, response.take::<RpcPing>(id))?),
+                RPC_VERSION => {
+                    cached.store(method, optional(method, response.take::<RpcVersion>(id))?)
+                }
+                PUBLIC_INFO => cached.store(
+                    method,
+                    optional(method, response.take::<CommonGetPublicInfo>(id))?,
+                ),
+                NODES => cached.store(
+                    method,
+                    optional(method, response.take::<CommonGetNodes>(id))?,
+                ),
+                NODES_LATEST_STATUS => cached.store(
+                    method,
+                    optional(method, response.take::<CommonGetNodesLatestStatus>(id))?,
+                ),
+                ME => cached.store(method, optional(method, response.take::<CommonGetMe>(id))?),
+                VERSION => cached.store(
+                    method,
+                    optional(method, response.take::<CommonGetVersion>(id))?,
+                ),
+                _ => {}
+            }
+        }
+    }
 
-    let common_get_public_info: CommonGetPublicInfo = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 5)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 5 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 5 的响应: {e}"),
-    })?;
-
-    let common_get_nodes: CommonGetNodes = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 6)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 6 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 6 的响应: {e}"),
-    })?;
+    let all_info = AllInfo {
+        rpc_help: cached.get(RPC_HELP).unwrap_or_default(),
+        rpc_methods: cached.get(RPC_METHODS).unwrap_or_default(),
+        rpc_ping: cached.get(RPC_PING).unwrap_or_default(),
+        rpc_version: cached.get(RPC_VERSION).unwrap_or_default(),
+        common_public_info: cached.get(PUBLIC_INFO).unwrap_or_default(),
+        common_nodes: cached.get(NODES).unwrap_or_default(),
+        common_nodes_latest_status: cached.get(NODES_LATEST_STATUS).unwrap_or_default(),
+        common_me: cached.get(ME).unwrap_or_default(),
+        common_version: cached.get(VERSION).unwrap_or_default(),
+    };
 
-    let common_get_nodes_latest_status: CommonGetNodesLatestStatus = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 7)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 7 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 7 的响应: {e}"),
-    })?;
+    if methods.contains(&NODES_LATEST_STATUS) {
+        uptime::observe(&all_info.common_nodes_latest_status);
+    }
 
-    let common_get_me: CommonGetMe = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 8)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 8 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 8 的响应: {e}"),
-    })?;
+    Ok(all_info)
+}
 
-    let common_get_version: CommonGetVersion = serde_json::from_value(
-        json_rpc_response_body
-            .iter()
-            .find(|response| response.id == 9)
-            .ok_or_else(|| ErrorType::JsonParseError {
-                error: ErrorString::from("Json 解析错误: 未找到 id 为 9 的响应"),
-            })?
-            .result
-            .clone(),
-    )
-    .map_err(|e| ErrorType::JsonParseError {
-        error: format!("Json 解析错误: 未找到 id 为 9 的响应: {e}"),
-    })?;
+/// 连接时记录的 `rpc.methods`，未记录时返回 `None`
+fn supported_methods(monitor: &Monitor) -> Option<RpcMethods> {
+    monitor
+        .rpc_methods
+        .as_deref()
+        .and_then(|methods| serde_json::from_str::<RpcMethods>(methods).ok())
+        .filter(|methods| !methods.is_empty())
+}
 
-    Ok(AllInfo {
-        rpc_help,
-        rpc_methods,
-        rpc_ping,
-        rpc_version,
-        common_public_info: common_get_public_info,
-        common_nodes: common_get_nodes,
-        common_nodes_latest_status: common_get_nodes_latest_status,
-        common_me: common_get_me,
-        common_version: common_get_version,
-    })
+fn optional<T: Default>(method: &str, result: Result<T, ErrorType>) -> Result<T, ErrorType> {
+    match result {
+        Err(e @ ErrorType::JsonRpcError { .. }) if OPTIONAL_METHODS.contains(&method) => {
+            debug!("可选方法 {method} 请求失败，使用默认值: {e}");
+            Ok(T::default())
+        }
+        result => result,
+    }
 }
 
 pub type RpcHelp = Vec<RpcHelpSingle>;
@@ -233,7 +185,7 @@ pub type RpcPing = String;
 pub type RpcVersion = String;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct CommonGetPublicInfo {
     #[serde(rename = "allow_cors")]
     pub allow_cors: bool,
@@ -264,7 +216,7 @@ pub type NodeUuid = String;
 pub type CommonGetNodes = HashMap<NodeUuid, CommonGetNodesSingle>;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct CommonGetNodesSingle {
     pub uuid: String,
     pub name: String,
@@ -280,6 +232,10 @@ pub struct CommonGetNodesSingle {
     #[serde(rename = "gpu_name")]
     pub gpu_name: String,
     pub region: String,
+    /// Komari 后台设置的排序权重
+    pub weight: i64,
+    /// 在 Komari 后台设置为隐藏，仅管理员可见
+    pub hidden: bool,
     #[serde(rename = "mem_total")]
     pub mem_total: i64,
     #[serde(rename = "swap_total")]
@@ -328,6 +284,9 @@ pub struct CommonGetNodesLatestStatusSingle {
     pub connections: i64,
     #[serde(rename = "connections_udp")]
     pub connections_udp: i64,
+    /// 运行时间，单位为秒
+    #[serde(default)]
+    pub uptime: i64,
     pub online: bool,
 }
 
@@ -347,7 +306,7 @@ pub struct CommonGetMe {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct CommonGetVersion {
     pub version: String,
     pub hash: String,
@@ -0,0 +1,159 @@
+use crate::utils::ErrorType;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+pub static RETRY_CONFIG: OnceLock<RetryConfig> = OnceLock::new();
+
+static BREAKERS: LazyLock<Mutex<HashMap<String, Breaker>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// 单次请求的最大尝试次数
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间，之后每次翻倍并加入随机抖动
+    pub base_delay_ms: u64,
+    /// 连续失败多少次后熔断
+    pub failure_threshold: u32,
+    /// 熔断后多久允许再次尝试
+    pub open_secs: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 200,
+            failure_threshold: 3,
+            open_secs: 60,
+        }
+    }
+}
+
+/// 单次尝试的失败类型
+pub enum RequestFailure {
+    /// 网络错误、超时或 5xx，可以重试
+    Transient(ErrorType),
+    /// 实例有响应但结果无效，重试无意义
+    Fatal(ErrorType),
+}
+
+impl From<reqwest::Error> for RequestFailure {
+    fn from(e: reqwest::Error) -> Self {
+        let transient = !e.is_decode()
+            && e.status()
+                .is_none_or(|status| status.is_server_error() || status.as_u16() == 429);
+
+        let error = ErrorType::RequestError {
+            error: e.to_string(),
+        };
+
+        if transient {
+            RequestFailure::Transient(error)
+        } else {
+            RequestFailure::Fatal(error)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    unreachable_since: Option<SystemTime>,
+    open_until: Option<Instant>,
+}
+
+fn backoff(config: &RetryConfig, attempt: u32) -> Duration {
+    let base = config
+        .base_delay_ms
+        .saturating_mul(1 << attempt.saturating_sub(1).min(10));
+    // 在 [base / 2, base) 之间随机，避免多个请求同时重试
+    let jitter = RandomState::new().hash_one(Instant::now()) % (base / 2).max(1);
+    Duration::from_millis(base / 2 + jitter)
+}
+
+/// 带重试与熔断地执行幂等请求，`key` 为实例地址
+pub async fn with_retry<T, F, Fut>(key: &str, mut request: F) -> Result<T, ErrorType>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RequestFailure>>,
+{
+    let config = RETRY_CONFIG.get_or_init(RetryConfig::default);
+
+    if let Some(since) = open_since(key) {
+        return Err(ErrorType::InstanceUnreachable { since });
+    }
+
+    let mut last_error = None;
+    for attempt in 1..=config.max_attempts.max(1) {
+        match request().await {
+            Ok(value) => {
+                record_success(key);
+                return Ok(value);
+            }
+            Err(RequestFailure::Fatal(e)) => {
+                record_success(key);
+                return Err(e);
+            }
+            Err(RequestFailure::Transient(e)) => {
+                debug!("请求失败 (第 {attempt} 次): {e}");
+                last_error = Some(e);
+                if attempt < config.max_attempts {
+                    tokio::time::sleep(backoff(config, attempt)).await;
+                }
+            }
+        }
+    }
+
+    if let Some(e) = last_error {
+        error!("{e}");
+    }
+
+    Err(ErrorType::InstanceUnreachable {
+        since: record_failure(key, config),
+    })
+}
+
+/// 熔断中时返回实例开始无法访问的时间
+fn open_since(key: &str) -> Option<SystemTime> {
+    let breakers = BREAKERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let breaker = breakers.get(key)?;
+
+    breaker
+        .open_until
+        .is_some_and(|open_until| Instant::now() < open_until)
+        .then_some(breaker.unreachable_since)
+        .flatten()
+}
+
+fn record_success(key: &str) {
+    BREAKERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .remove(key);
+}
+
+fn record_failure(key: &str, config: &RetryConfig) -> SystemTime {
+    let mut breakers = BREAKERS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let breaker = breakers.entry(key.to_string()).or_default();
+
+    breaker.consecutive_failures += 1;
+    let since = *breaker
+        .unreachable_since
+        .get_or_insert_with(SystemTime::now);
+
+    if breaker.consecutive_failures >= config.failure_threshold {
+        breaker.open_until = Some(Instant::now() + Duration::from_secs(config.open_secs));
+    }
+
+    since
+}
@@ -0,0 +1,120 @@
+use crate::json_rpc::get_node_id::SortedNodeList;
+use crate::json_rpc::query::{AllInfo, CommonGetNodesSingle};
+use crate::utils::ErrorType;
+use regex::RegexBuilder;
+
+/// 选择列表中最多显示的节点数
+pub const MAX_CHOICES: usize = 20;
+
+/// 节点搜索的匹配方式
+enum Matcher {
+    /// `/PATTERN/` 形式的正则表达式
+    Regex(regex::Regex),
+    /// 不区分大小写的子串，或按顺序包含所有字符的模糊匹配
+    Text(String),
+}
+
+impl Matcher {
+    fn new(query: &str) -> Result<Self, ErrorType> {
+        let query = query.trim();
+
+        if let Some(pattern) = query
+            .strip_prefix('/')
+            .and_then(|query| query.strip_suffix('/'))
+            .filter(|pattern| !pattern.is_empty())
+        {
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .size_limit(1 << 20)
+                .build()
+                .map_err(|e| ErrorType::GeneralError {
+                    error: format!("无效的正则表达式: {e}"),
+                })?;
+            return Ok(Matcher::Regex(regex));
+        }
+
+        if query.is_empty() {
+            return Err(ErrorType::GeneralError {
+                error: String::from("请输入要搜索的节点名称"),
+            });
+        }
+
+        Ok(Matcher::Text(query.to_lowercase()))
+    }
+
+    /// 匹配程度，数值越小越精确，不匹配时返回 `None`
+    fn score(&self, node: &CommonGetNodesSingle) -> Option<u8> {
+        let fields = [
+            node.name.as_str(),
+            node.region.as_str(),
+            node.group.as_deref().unwrap_or_default(),
+            node.tags.as_deref().unwrap_or_default(),
+            node.os.as_str(),
+            node.cpu_name.as_str(),
+        ];
+
+        match self {
+            Matcher::Regex(regex) => fields
+                .iter()
+                .any(|field| regex.is_match(field))
+                .then_some(0),
+            Matcher::Text(query) => {
+                let name = node.name.to_lowercase();
+                if name == *query {
+                    Some(0)
+                } else if name.contains(query.as_str()) {
+                    Some(1)
+                } else if fields
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(query.as_str()))
+                {
+                    Some(2)
+                } else if is_subsequence(query, &name) {
+                    Some(3)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// `query` 的所有字符是否按顺序出现在 `text` 中
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut text = text.chars();
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|c| text.any(|t| t == c))
+}
+
+/// 在节点列表中搜索，返回最佳匹配的 `(短 ID, 节点名称)`
+///
+/// 有精确匹配名称的节点时只返回精确匹配，否则返回同一匹配程度的所有节点
+pub fn search_nodes(
+    query: &str,
+    all_info: &AllInfo,
+    node_list: &SortedNodeList,
+) -> Result<Vec<(i32, String)>, ErrorType> {
+    let matcher = Matcher::new(query)?;
+
+    let scored = node_list
+        .iter()
+        .filter_map(|(node_id, node_uuid, _)| {
+            let node = all_info.common_nodes.get(node_uuid)?;
+            Some((matcher.score(node)?, *node_id, node.name.clone()))
+        })
+        .collect::<Vec<_>>();
+
+    let Some(best) = scored.iter().map(|(score, _, _)| *score).min() else {
+        return Err(ErrorType::GeneralError {
+            error: format!("未找到与 {query} 匹配的节点"),
+        });
+    };
+
+    Ok(scored
+        .into_iter()
+        .filter(|(score, _, _)| *score == best)
+        .map(|(_, node_id, name)| (node_id, name))
+        .collect())
+}
@@ -1,25 +1,48 @@
-use crate::json_rpc::bytes_to_pretty_string;
-use crate::json_rpc::get_node_id::get_node_id_list;
-use crate::json_rpc::query::AllInfo;
+use crate::json_rpc::get_node_id::{NodeSort, SortedNodeList, get_node_id_list};
+use crate::json_rpc::groups::node_group;
+use crate::json_rpc::query::{NODE_LIST_METHODS, STATUS_METHODS};
+use crate::json_rpc::search::{MAX_CHOICES, search_nodes};
+use crate::json_rpc::uptime::recently_rebooted;
+use crate::node_browser::{BrowserState, back_button};
+use crate::settings::{Language, Settings, elapsed_since};
 use crate::utils::ErrorType;
 use crate::{MessageString, TelegramId};
 use reqwest::Url;
+use std::time::Duration;
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 
+/// 单个节点的状态卡片
+pub struct StatusCard {
+    pub message: MessageString,
+    /// 翻页范围内的节点
+    pub node_list: SortedNodeList,
+    /// 实际渲染的节点 ID
+    pub node_id: i32,
+    /// 节点所在的分组
+    pub group: Option<String>,
+    /// 是否仅在分组内翻页
+    pub in_group: bool,
+}
+
+/// 渲染短 ID 为 `node_id` 的节点状态，为 0 时使用第一个节点
+///
+/// `in_group` 为真时，翻页范围限制在该节点所在的分组内
 pub async fn status_with_id(
     telegram_id: TelegramId,
-    index: u32,
-) -> Result<(MessageString, AllInfo), ErrorType> {
-    let (_, all_info, node_id_list) = get_node_id_list(telegram_id).await?;
-
-    let vec_index: usize = match index {
-        0 | 1 => 0,
-        _ => (index - 1) as usize,
-    };
+    node_id: i32,
+    in_group: bool,
+    settings: &Settings,
+) -> Result<StatusCard, ErrorType> {
+    let (_, all_info, node_id_list) =
+        get_node_id_list(telegram_id, &STATUS_METHODS, settings, NodeSort::Id).await?;
 
-    let (node_uuid, node_latest_info) = node_id_list
-        .get(vec_index)
+    let (node_id, node_uuid, node_latest_info) = node_id_list
+        .iter()
+        .find(|node| node_id == 0 || node.0 == node_id)
         .ok_or(ErrorType::UnableToFindServerByUUID)?;
+    let node_id = *node_id;
+    let reported = node_latest_info.is_some();
+    let node_latest_info = node_latest_info.clone().unwrap_or_default();
 
     let node_info = all_info
         .common_nodes
@@ -28,177 +51,329 @@ pub async fn status_with_id(
         .cloned()
         .ok_or(ErrorType::UnableToFindServerByUUID)?;
 
+    let fmt = settings.number_format();
+    // 从未上报的节点总量为 0，占用率显示为 0
+    let percent = |used: i64, total: i64| {
+        if total > 0 {
+            used as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
     let (ram_used, ram_total, ram_usage) = {
         let ram_used = node_latest_info.ram;
         let ram_total = node_latest_info.ram_total;
-        let ram_usage = (ram_used as f64 / ram_total as f64) * 100.0;
+        let ram_usage = percent(ram_used, ram_total);
         (
-            bytes_to_pretty_string(ram_used),
-            bytes_to_pretty_string(ram_total),
-            ram_usage,
+            fmt.bytes(ram_used),
+            fmt.bytes(ram_total),
+            fmt.number(ram_usage),
         )
     };
 
     let (swap_used, swap_total, swap_usage) = {
         let swap_used = node_latest_info.swap;
         let swap_total = node_latest_info.swap_total;
-        let swap_usage = (swap_used as f64 / swap_total as f64) * 100.0;
+        let swap_usage = percent(swap_used, swap_total);
         (
-            bytes_to_pretty_string(swap_used),
-            bytes_to_pretty_string(swap_total),
-            swap_usage,
+            fmt.bytes(swap_used),
+            fmt.bytes(swap_total),
+            fmt.number(swap_usage),
         )
     };
 
     let (disk_used, disk_total, disk_usage) = {
         let disk_used = node_latest_info.disk;
         let disk_total = node_latest_info.disk_total;
-        let disk_usage = (disk_used as f64 / disk_total as f64) * 100.0;
+        let disk_usage = percent(disk_used, disk_total);
         (
-            bytes_to_pretty_string(disk_used),
-            bytes_to_pretty_string(disk_total),
-            disk_usage,
+            fmt.bytes(disk_used),
+            fmt.bytes(disk_total),
+            fmt.number(disk_usage),
         )
     };
 
-    let msg = format!(
-        r"{title} | {region} | {name}
+    // 以最近一次上报的时间为准，而非节点信息的修改时间
+    let report_elapsed = elapsed_since(&node_latest_info.time);
+    let update_at = match report_elapsed {
+        Some(elapsed) => Some(format!(
+            "{} ({})",
+            settings.format_time(&node_latest_info.time),
+            settings.format_ago(elapsed)
+        )),
+        None if node_latest_info.time.is_empty() => None,
+        None => Some(node_latest_info.time.clone()),
+    };
+    let stale = if !reported {
+        format!(
+            "\n{}",
+            settings
+                .language
+                .pick("NEVER REPORTED: 节点从未上报数据", "NEVER REPORTED")
+        )
+    } else if !node_latest_info.online {
+        format!(
+            "\n{}",
+            settings.language.pick("OFFLINE: 节点已离线", "OFFLINE")
+        )
+    } else if report_elapsed.is_some_and(|elapsed| elapsed > settings.stale_after) {
+        let threshold = settings.format_duration(settings.stale_after);
+        match settings.language {
+            Language::Zh => format!("\nSTALE: 数据已过期，超过 {threshold} 未上报"),
+            Language::En => format!("\nSTALE: no report for over {threshold}"),
+        }
+    } else {
+        String::new()
+    };
+
+    let uptime = if node_latest_info.uptime > 0 {
+        settings.format_duration(Duration::from_secs(node_latest_info.uptime as u64))
+    } else {
+        String::from("-")
+    };
+    let rebooted = if recently_rebooted(node_uuid) {
+        format!(
+            " ({})",
+            settings.language.pick("最近重启", "recently rebooted")
+        )
+    } else {
+        String::new()
+    };
+
+    // 未上报 GPU 或温度的节点不显示对应行
+    let gpu_usage = if node_latest_info.gpu > 0.0 {
+        format!("\nGPU: `{}%`", fmt.number(node_latest_info.gpu))
+    } else {
+        String::new()
+    };
+    let temperature = if node_latest_info.temp > 0 {
+        format!("\nTEMP: `{} °C`", fmt.integer(node_latest_info.temp))
+    } else {
+        String::new()
+    };
+
+    let msg = if settings.compact {
+        format!(
+            r"{name} | {region}{stale}
+
+CPU: `{cpu_usage}%` LOAD: `{load1}`{gpu_usage}{temperature}
+RAM: `{ram_used}` / `{ram_total}` `{ram_usage}%`
+DISK: `{disk_used}` / `{disk_total}` `{disk_usage}%`
+NET: `{net_down}` / `{net_up}`
+UPTIME: `{uptime}`{rebooted}{update_at}",
+            region = node_info.region,
+            name = node_info.name,
+            cpu_usage = fmt.number(node_latest_info.cpu),
+            load1 = fmt.number(node_latest_info.load),
+            net_down = fmt.speed(node_latest_info.net_in),
+            net_up = fmt.speed(node_latest_info.net_out),
+            update_at = update_at
+                .map(|update_at| format!("\nUPDATE AT: `{update_at}`"))
+                .unwrap_or_default(),
+        )
+    } else {
+        format!(
+            r"{title} | {region} | {name}{stale}
 
 CPU: `{cpu_name}` @ `{cpu_cores} Cores`{gpu_name}
 ARCH: `{arch}`
 VIRT: `{virtualization}`
 OS: `{os}`
 KERN: `{kernel_version}`
-UPTIME: `{uptime}`
+UPTIME: `{uptime}`{rebooted}
 
-CPU: `{cpu_usage:.2}%`
-RAM: `{ram_used}` / `{ram_total}` `{ram_usage:.2}%`
-SWAP: `{swap_used}` / `{swap_total}` `{swap_usage:.2}%`
-DISK: `{disk_used}` / `{disk_total}` `{disk_usage:.2}%`
+CPU: `{cpu_usage}%`{gpu_usage}{temperature}
+RAM: `{ram_used}` / `{ram_total}` `{ram_usage}%`
+SWAP: `{swap_used}` / `{swap_total}` `{swap_usage}%`
+DISK: `{disk_used}` / `{disk_total}` `{disk_usage}%`
 
-LOAD: `{load1:.2}` / `{load5:.2}` / `{load15:.2}`
+LOAD: `{load1}` / `{load5}` / `{load15}`
 PROC: `{processes}`
 
 NET: `{total_net_down}` / `{total_net_up}`
-UP: `{net_up:.2} Mbps`
-DOWN: `{net_down:.2} Mbps`
+UP: `{net_up}`
+DOWN: `{net_down}`
 CONN: `{total_tcp_connections} TCP` / `{total_udp_connections} UDP`{update_at}",
-        title = all_info.common_public_info.sitename,
-        region = node_info.region,
-        name = node_info.name,
-        arch = node_info.arch,
-        cpu_name = node_info.cpu_name,
-        cpu_cores = node_info.cpu_cores,
-        virtualization = node_info.virtualization,
-        os = node_info.os,
-        kernel_version = node_info.kernel_version,
-        uptime = 0,
-        cpu_usage = node_latest_info.cpu,
-        load1 = node_latest_info.load,
-        load5 = node_latest_info.load5,
-        load15 = node_latest_info.load15,
-        processes = node_latest_info.process,
-        total_net_down = bytes_to_pretty_string(node_latest_info.net_total_down),
-        total_net_up = bytes_to_pretty_string(node_latest_info.net_total_up),
-        net_down = node_latest_info.net_in as f64 / 125000.0,
-        net_up = node_latest_info.net_out as f64 / 125000.0,
-        total_tcp_connections = node_latest_info.connections,
-        total_udp_connections = node_latest_info.connections_udp,
-        update_at = {
-            if let Some(updated_at) = node_info.updated_at {
-                format!(
-                    "
-
-UPDATE AT: `{updated_at}`"
-                )
-            } else {
-                String::new()
-            }
-        },
-        gpu_name = {
-            if node_info.gpu_name.is_empty() {
-                String::new()
-            } else {
-                format!(
-                    "
+            title = all_info.common_public_info.sitename,
+            region = node_info.region,
+            name = node_info.name,
+            arch = node_info.arch,
+            cpu_name = node_info.cpu_name,
+            cpu_cores = node_info.cpu_cores,
+            virtualization = node_info.virtualization,
+            os = node_info.os,
+            kernel_version = node_info.kernel_version,
+            cpu_usage = fmt.number(node_latest_info.cpu),
+            load1 = fmt.number(node_latest_info.load),
+            load5 = fmt.number(node_latest_info.load5),
+            load15 = fmt.number(node_latest_info.load15),
+            processes = fmt.integer(node_latest_info.process),
+            total_net_down = fmt.bytes(node_latest_info.net_total_down),
+            total_net_up = fmt.bytes(node_latest_info.net_total_up),
+            net_down = fmt.speed(node_latest_info.net_in),
+            net_up = fmt.speed(node_latest_info.net_out),
+            total_tcp_connections = fmt.integer(node_latest_info.connections),
+            total_udp_connections = fmt.integer(node_latest_info.connections_udp),
+            update_at = update_at
+                .map(|update_at| format!("\n\nUPDATE AT: `{update_at}`"))
+                .unwrap_or_default(),
+            gpu_name = {
+                if node_info.gpu_name.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        "
 GPU: `{}`",
-                    node_info.gpu_name
-                )
+                        node_info.gpu_name
+                    )
+                }
             }
-        }
-    );
+        )
+    };
+
+    let group = node_group(&node_info).map(str::to_string);
+    let node_list = if in_group {
+        node_id_list
+            .iter()
+            .filter(|(_, node_uuid, _)| {
+                all_info.common_nodes.get(node_uuid).and_then(node_group) == group.as_deref()
+            })
+            .cloned()
+            .collect()
+    } else {
+        node_id_list.clone()
+    };
 
-    Ok((msg, all_info))
+    Ok(StatusCard {
+        message: msg,
+        node_list,
+        node_id,
+        group,
+        in_group,
+    })
 }
 
+/// 按名称搜索节点的结果
+pub enum NodeSearchResult {
+    /// 仅有一个节点匹配，直接返回其状态
+    Single(StatusCard),
+    /// 多个节点匹配，需要用户选择，元素为 `(短 ID, 节点名称)`
+    Multiple(Vec<(i32, String)>),
+}
+
+/// 按名称、地区、分组、标签、系统及 CPU 搜索节点，`/PATTERN/` 为正则表达式
 pub async fn get_node_id_by_name(
     telegram_id: TelegramId,
     name: String,
-) -> Result<(MessageString, AllInfo, i32), ErrorType> {
-    let (message_str, _, _) = get_node_id_list(telegram_id).await?;
-
-    let mut selected_node_id = -1;
-    for line in message_str.lines() {
-        let (node_id, node_name) = line.split_once(" - ").ok_or(ErrorType::GeneralError {
-            error: String::from("无法解析节点 ID"),
-        })?;
-
-        selected_node_id = if node_name.contains(name.as_str()) {
-            node_id
-                .trim_matches('`')
-                .parse::<i32>()
-                .map_err(|_| ErrorType::GeneralError {
-                    error: String::from("无法解析节点 ID"),
-                })?
-        } else {
-            continue;
-        };
-    }
+    settings: &Settings,
+) -> Result<NodeSearchResult, ErrorType> {
+    let (_, all_info, node_list) =
+        get_node_id_list(telegram_id, &NODE_LIST_METHODS, settings, NodeSort::Id).await?;
 
-    let (msg, all_info) = status_with_id(telegram_id, selected_node_id as u32).await?;
+    let matches = search_nodes(&name, &all_info, &node_list)?;
 
-    Ok((msg, all_info, selected_node_id))
+    match matches.as_slice() {
+        [(node_id, _)] => {
+            let card = status_with_id(telegram_id, *node_id, false, settings).await?;
+            Ok(NodeSearchResult::Single(card))
+        }
+        _ => Ok(NodeSearchResult::Multiple(matches)),
+    }
 }
 
+/// 多个节点匹配时供用户选择的按钮
+#[must_use]
+pub fn make_keyboard_for_choices(
+    telegram_id: TelegramId,
+    choices: &[(i32, String)],
+) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(
+        choices
+            .iter()
+            .take(MAX_CHOICES)
+            .map(|(node_id, name)| {
+                vec![InlineKeyboardButton::callback(
+                    format!("{node_id} - {name}"),
+                    format!("{telegram_id}-{node_id}"),
+                )]
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// 单个节点状态的翻页按钮，回调数据为 `{telegram_id}-{短 ID}`
+///
+/// 分组内翻页时附加 `-g`，从节点列表打开时附加 `-b{列表状态}` 并显示返回按钮
 pub async fn make_keyboard_for_single(
-    now_id: i32,
     telegram_id: i64,
-    all_info: &AllInfo,
+    card: &StatusCard,
+    back: Option<BrowserState>,
+    language: Language,
 ) -> InlineKeyboardMarkup {
-    let max_server = all_info.common_nodes.iter().len();
+    let node_list = &card.node_list;
+    let now_id = card.node_id;
+    let back_suffix = back
+        .map(|back| format!("-b{}", back.encode()))
+        .unwrap_or_default();
+    let scope = if card.in_group {
+        format!("-g{back_suffix}")
+    } else {
+        back_suffix.clone()
+    };
+    let position = node_list.iter().position(|node| node.0 == now_id);
 
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
     let mut first_row = vec![];
 
-    let send_id = match now_id {
-        0 | 1 => (0, 2),
-        _ => (now_id - 1, now_id + 1),
-    };
-
-    if send_id.0 > 0 {
+    if let Some(prev) = position
+        .and_then(|position| position.checked_sub(1))
+        .and_then(|position| node_list.get(position))
+    {
         first_row.push(InlineKeyboardButton::callback(
             "<-",
-            format!("{}-{}", telegram_id, send_id.0),
+            format!("{}-{}{scope}", telegram_id, prev.0),
         ));
     }
 
     first_row.push(InlineKeyboardButton::url(
-        format!("{now_id} / {max_server}"),
+        format!(
+            "{} / {}",
+            position.map_or(0, |position| position + 1),
+            node_list.len()
+        ),
         Url::parse("https://t.me/komaritgbot").unwrap(),
     ));
 
-    if send_id.1 <= max_server as i32 {
+    if let Some(next) = position.and_then(|position| node_list.get(position + 1)) {
         first_row.push(InlineKeyboardButton::callback(
             "->",
-            format!("{}-{}", telegram_id, send_id.1),
+            format!("{}-{}{scope}", telegram_id, next.0),
         ));
     }
 
     keyboard.push(first_row);
-    keyboard.push(vec![InlineKeyboardButton::callback(
+
+    let mut second_row = vec![InlineKeyboardButton::callback(
         "Refresh",
-        format!("{telegram_id}-{now_id}"),
-    )]);
+        format!("{telegram_id}-{now_id}{scope}"),
+    )];
+    match (&card.group, card.in_group) {
+        (Some(group), false) => second_row.push(InlineKeyboardButton::callback(
+            format!("[{group}]"),
+            format!("{telegram_id}-{now_id}-g{back_suffix}"),
+        )),
+        (_, true) => second_row.push(InlineKeyboardButton::callback(
+            "[*]",
+            format!("{telegram_id}-{now_id}{back_suffix}"),
+        )),
+        (None, false) => {}
+    }
+    keyboard.push(second_row);
+
+    if let Some(back) = back {
+        keyboard.push(vec![back_button(telegram_id, back, language)]);
+    }
 
     InlineKeyboardMarkup::new(keyboard)
 }
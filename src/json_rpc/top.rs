@@ -0,0 +1,155 @@
+use crate::json_rpc::get_node_id::{NodeSort, get_node_id_list};
+use crate::json_rpc::groups::node_group;
+use crate::json_rpc::query::{CommonGetNodesLatestStatusSingle, STATUS_METHODS};
+use crate::json_rpc::search::MAX_CHOICES;
+use crate::json_rpc::status::make_keyboard_for_choices;
+use crate::settings::Settings;
+use crate::utils::ErrorType;
+use crate::{MessageString, TelegramId};
+use teloxide::types::InlineKeyboardMarkup;
+
+pub const DEFAULT_COUNT: usize = 5;
+
+/// `/top` 的排序指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopMetric {
+    Cpu,
+    Ram,
+    Disk,
+    Load,
+    /// 当前上下行网速之和
+    Net,
+    /// TCP 与 UDP 连接数之和
+    Conn,
+    /// 累计上下行流量之和
+    Traffic,
+}
+
+impl TopMetric {
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "cpu" => Some(TopMetric::Cpu),
+            "ram" | "mem" => Some(TopMetric::Ram),
+            "disk" => Some(TopMetric::Disk),
+            "load" => Some(TopMetric::Load),
+            "net" => Some(TopMetric::Net),
+            "conn" => Some(TopMetric::Conn),
+            "traffic" => Some(TopMetric::Traffic),
+            _ => None,
+        }
+    }
+
+    const fn title(self) -> &'static str {
+        match self {
+            TopMetric::Cpu => "CPU",
+            TopMetric::Ram => "RAM",
+            TopMetric::Disk => "DISK",
+            TopMetric::Load => "LOAD",
+            TopMetric::Net => "NET",
+            TopMetric::Conn => "CONN",
+            TopMetric::Traffic => "TRAFFIC",
+        }
+    }
+
+    fn value(self, status: &CommonGetNodesLatestStatusSingle) -> f64 {
+        let percent = |used: i64, total: i64| {
+            if total > 0 {
+                used as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            }
+        };
+
+        match self {
+            TopMetric::Cpu => status.cpu,
+            TopMetric::Ram => percent(status.ram, status.ram_total),
+            TopMetric::Disk => percent(status.disk, status.disk_total),
+            TopMetric::Load => status.load,
+            TopMetric::Net => (status.net_in + status.net_out) as f64,
+            TopMetric::Conn => (status.connections + status.connections_udp) as f64,
+            TopMetric::Traffic => (status.net_total_up + status.net_total_down) as f64,
+        }
+    }
+
+    fn display(self, status: &CommonGetNodesLatestStatusSingle, settings: &Settings) -> String {
+        let fmt = settings.number_format();
+        let value = self.value(status);
+
+        match self {
+            TopMetric::Cpu | TopMetric::Ram | TopMetric::Disk => {
+                format!("{}%", fmt.number(value))
+            }
+            TopMetric::Load => fmt.number(value),
+            TopMetric::Net => format!(
+                "{} / {}",
+                fmt.speed(status.net_in),
+                fmt.speed(status.net_out)
+            ),
+            TopMetric::Conn => fmt.integer(status.connections + status.connections_udp),
+            TopMetric::Traffic => format!(
+                "{} / {}",
+                fmt.bytes(status.net_total_down),
+                fmt.bytes(status.net_total_up)
+            ),
+        }
+    }
+}
+
+/// 按 `metric` 排名在线节点，`group` 不为空时仅统计该分组
+pub async fn top_nodes(
+    telegram_id: TelegramId,
+    metric: TopMetric,
+    count: usize,
+    group: Option<&str>,
+    settings: &Settings,
+) -> Result<(MessageString, InlineKeyboardMarkup), ErrorType> {
+    let (_, all_info, node_list) =
+        get_node_id_list(telegram_id, &STATUS_METHODS, settings, NodeSort::Id).await?;
+    let count = count.clamp(1, MAX_CHOICES);
+
+    let mut ranked = node_list
+        .iter()
+        .filter_map(|(node_id, node_uuid, status)| {
+            let status = status.as_ref().filter(|status| status.online)?;
+            let node = all_info.common_nodes.get(node_uuid)?;
+            if let Some(group) = group
+                && !node_group(node)
+                    .is_some_and(|node_group| node_group.eq_ignore_ascii_case(group))
+            {
+                return None;
+            }
+            Some((*node_id, node.name.clone(), status))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| metric.value(b.2).total_cmp(&metric.value(a.2)));
+    ranked.truncate(count);
+
+    if ranked.is_empty() {
+        return Err(ErrorType::GeneralError {
+            error: String::from("没有可排名的在线节点"),
+        });
+    }
+
+    let mut message = format!(
+        "{} TOP {} {}{}\n\n",
+        all_info.common_public_info.sitename,
+        ranked.len(),
+        metric.title(),
+        group.map(|group| format!(" | {group}")).unwrap_or_default()
+    );
+    for (rank, (node_id, name, status)) in ranked.iter().enumerate() {
+        message.push_str(&format!(
+            "{}. `{node_id}` {name}: `{}`\n",
+            rank + 1,
+            metric.display(status, settings)
+        ));
+    }
+
+    let choices = ranked
+        .into_iter()
+        .map(|(node_id, name, _)| (node_id, name))
+        .collect::<Vec<_>>();
+
+    Ok((message, make_keyboard_for_choices(telegram_id, &choices)))
+}
@@ -1,10 +1,16 @@
 use crate::db::{DB_POOL, query_monitor_by_telegram_id};
-use crate::json_rpc::bytes_to_pretty_string;
-use crate::json_rpc::query::AllInfo;
+use crate::json_rpc::groups::retain_group;
+use crate::json_rpc::query::{AllInfo, STATUS_METHODS, get_info};
+use crate::settings::Settings;
 use crate::utils::ErrorType;
 use crate::{MessageString, TelegramId};
 
-pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, AllInfo), ErrorType> {
+/// 所有节点或 `group` 分组内节点的汇总状态
+pub async fn total_status(
+    telegram_id: TelegramId,
+    group: Option<&str>,
+    settings: &Settings,
+) -> Result<(MessageString, AllInfo), ErrorType> {
     let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
         error: "无法获取数据库".to_string(),
     })?;
@@ -13,7 +19,15 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
         return Err(ErrorType::UserNotConnected);
     };
 
-    let all_info = crate::json_rpc::query::get_all_info(&monitor.monitor_url).await?;
+    let mut all_info = get_info(&monitor, &STATUS_METHODS).await?;
+    if let Some(group) = group {
+        retain_group(&mut all_info, group)?;
+    }
+    let title = match group {
+        Some(group) => format!("{} | {group}", all_info.common_public_info.sitename),
+        None => all_info.common_public_info.sitename.clone(),
+    };
+    let fmt = settings.number_format();
 
     let (online_nodes_count, total_nodes_count, percent_online) = {
         let online_nodes_count = all_info
@@ -29,7 +43,11 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
         } else {
             0.0
         };
-        (online_nodes_count, total_nodes_count, percent_online)
+        (
+            online_nodes_count,
+            total_nodes_count,
+            fmt.number(percent_online),
+        )
     };
 
     let (avg_load1, avg_load5, avg_load15) = {
@@ -51,7 +69,7 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
             .map(|node| node.load15)
             .sum::<f64>()
             / online_nodes_count as f64;
-        (load1, load5, load15)
+        (fmt.number(load1), fmt.number(load5), fmt.number(load15))
     };
 
     let (total_used_ram, total_total_ram, avg_ram_usage) = {
@@ -72,9 +90,9 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
         };
 
         (
-            bytes_to_pretty_string(total_used_ram),
-            bytes_to_pretty_string(total_total_ram),
-            avg_ram_usage,
+            fmt.bytes(total_used_ram),
+            fmt.bytes(total_total_ram),
+            fmt.number(avg_ram_usage),
         )
     };
 
@@ -96,9 +114,9 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
         };
 
         (
-            bytes_to_pretty_string(total_used_swap),
-            bytes_to_pretty_string(total_total_swap),
-            avg_swap_usage,
+            fmt.bytes(total_used_swap),
+            fmt.bytes(total_total_swap),
+            fmt.number(avg_swap_usage),
         )
     };
 
@@ -120,9 +138,9 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
         };
 
         (
-            bytes_to_pretty_string(total_used_disk),
-            bytes_to_pretty_string(total_total_disk),
-            avg_disk_usage,
+            fmt.bytes(total_used_disk),
+            fmt.bytes(total_total_disk),
+            fmt.number(avg_disk_usage),
         )
     };
 
@@ -166,45 +184,95 @@ pub async fn total_status(telegram_id: TelegramId) -> Result<(MessageString, All
             .sum::<i64>();
 
         (
-            bytes_to_pretty_string(total_total_net_down),
-            bytes_to_pretty_string(total_total_net_up),
-            total_net_down as f64 / 125000.0,
-            total_net_up as f64 / 125000.0,
-            total_tcp_connections,
-            total_udp_connections,
+            fmt.bytes(total_total_net_down),
+            fmt.bytes(total_total_net_up),
+            fmt.speed(total_net_down),
+            fmt.speed(total_net_up),
+            fmt.integer(total_tcp_connections),
+            fmt.integer(total_udp_connections),
         )
     };
 
-    let msg = format!(
-        r"{title} 总览
+    let avg_cpu_usage = fmt.number(
+        all_info
+            .common_nodes_latest_status
+            .values()
+            .map(|node| node.cpu)
+            .sum::<f64>()
+            / online_nodes_count as f64,
+    );
+
+    // 未上报 GPU 或温度的节点不计入
+    let avg_gpu_usage = {
+        let gpu_usages = all_info
+            .common_nodes_latest_status
+            .values()
+            .map(|node| node.gpu)
+            .filter(|gpu| *gpu > 0.0)
+            .collect::<Vec<_>>();
+        if gpu_usages.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\nAVG GPU: `{}%`",
+                fmt.number(gpu_usages.iter().sum::<f64>() / gpu_usages.len() as f64)
+            )
+        }
+    };
+    let temperature = {
+        let temps = all_info
+            .common_nodes_latest_status
+            .values()
+            .map(|node| node.temp)
+            .filter(|temp| *temp > 0)
+            .collect::<Vec<_>>();
+        match temps.iter().max() {
+            Some(max_temp) => format!(
+                "\nTEMP: MAX `{} °C` / AVG `{} °C`",
+                fmt.integer(*max_temp),
+                fmt.number(temps.iter().sum::<i64>() as f64 / temps.len() as f64)
+            ),
+            None => String::new(),
+        }
+    };
+
+    let overview = settings.language.pick("总览", "Overview");
+
+    let msg = if settings.compact {
+        format!(
+            r"{title} {overview}
 
-ONLINE: `{online_nodes_count}` / `{total_nodes_count}` `{percent_online:.2}%`
+ONLINE: `{online_nodes_count}` / `{total_nodes_count}`
+AVG CPU: `{avg_cpu_usage}%`{avg_gpu_usage}{temperature}
+MEM: `{total_used_ram}` / `{total_total_ram}` `{avg_ram_usage}%`
+DISK: `{total_used_disk}` / `{total_total_disk}` `{avg_disk_usage}%`
+SPEED: `{total_net_down}` / `{total_net_up}`",
+        )
+    } else {
+        format!(
+            r"{title} {overview}
+
+ONLINE: `{online_nodes_count}` / `{total_nodes_count}` `{percent_online}%`
 CPU CORES: `{cores_count}`
-AVG CPU: `{avg_cpu_usage:.2}%`
-AVG LOAD: `{avg_load1:.2}` / `{avg_load5:.2}` / `{avg_load15:.2}`
+AVG CPU: `{avg_cpu_usage}%`
+AVG LOAD: `{avg_load1}` / `{avg_load5}` / `{avg_load15}`{avg_gpu_usage}{temperature}
 
-MEM: `{total_used_ram}` / `{total_total_ram}` `{avg_ram_usage:.2}%`
-SWAP: `{total_used_swap}` / `{total_total_swap}` `{avg_swap_usage:.2}%`
-DISK: `{total_used_disk}` / `{total_total_disk}` `{avg_disk_usage:.2}%`
+MEM: `{total_used_ram}` / `{total_total_ram}` `{avg_ram_usage}%`
+SWAP: `{total_used_swap}` / `{total_total_swap}` `{avg_swap_usage}%`
+DISK: `{total_used_disk}` / `{total_total_disk}` `{avg_disk_usage}%`
 
 DOWN: `{total_total_net_down}`
 UP: `{total_total_net_up}`
-DOWN SPEED: `{total_net_down:.2} Mbps`
-UP SPEED: `{total_net_up:.2} Mbps`
+DOWN SPEED: `{total_net_down}`
+UP SPEED: `{total_net_up}`
 CONN: `{total_tcp_connections} TCP` / `{total_udp_connections} UDP`",
-        title = all_info.common_public_info.sitename,
-        cores_count = all_info
-            .common_nodes
-            .values()
-            .map(|node| node.cpu_cores)
-            .sum::<i64>(),
-        avg_cpu_usage = all_info
-            .common_nodes_latest_status
-            .values()
-            .map(|node| node.cpu)
-            .sum::<f64>()
-            / online_nodes_count as f64,
-    );
+            cores_count = all_info
+                .common_nodes
+                .values()
+                .map(|node| node.cpu_cores)
+                .sum::<i64>(),
+        )
+    };
 
     Ok((msg, all_info))
 }
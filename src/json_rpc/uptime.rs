@@ -0,0 +1,62 @@
+use crate::json_rpc::query::CommonGetNodesLatestStatus;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// 检测到重启后，状态卡片显示提示的时长
+const REBOOT_INDICATOR_WINDOW: Duration = Duration::from_secs(3600);
+
+static UPTIMES: LazyLock<Mutex<HashMap<String, TrackedUptime>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedUptime {
+    uptime: i64,
+    rebooted_at: Option<Instant>,
+    seen_at: Instant,
+}
+
+/// 记录每次获取到的运行时间，运行时间比上次小即视为节点已重启
+///
+/// 节点 UUID 由 Komari 随机生成，同一节点被多个用户连接时共享记录。
+/// 超过提示时长未再获取到的节点 (已删除或已断开连接) 不再记录
+pub fn observe(latest_status: &CommonGetNodesLatestStatus) {
+    let mut uptimes = UPTIMES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    uptimes.retain(|_, tracked| tracked.seen_at.elapsed() < REBOOT_INDICATOR_WINDOW);
+
+    for (node_uuid, status) in latest_status {
+        // 未上报运行时间的节点无法判断
+        if status.uptime <= 0 {
+            continue;
+        }
+
+        let rebooted_at = match uptimes.get(node_uuid) {
+            Some(tracked) if status.uptime < tracked.uptime => Some(Instant::now()),
+            Some(tracked) => tracked.rebooted_at,
+            None => None,
+        };
+
+        uptimes.insert(
+            node_uuid.clone(),
+            TrackedUptime {
+                uptime: status.uptime,
+                rebooted_at,
+                seen_at: Instant::now(),
+            },
+        );
+    }
+}
+
+/// 节点是否在最近检测到重启
+#[must_use]
+pub fn recently_rebooted(node_uuid: &str) -> bool {
+    UPTIMES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(node_uuid)
+        .and_then(|tracked| tracked.rebooted_at)
+        .is_some_and(|rebooted_at| rebooted_at.elapsed() < REBOOT_INDICATOR_WINDOW)
+}
@@ -3,19 +3,53 @@
 mod db;
 mod http_webhook;
 mod json_rpc;
+mod node_browser;
+mod node_changes;
+mod onboarding;
+mod rate_limit;
+mod settings;
+mod ssrf;
 mod utils;
+mod weekly_report;
 
 use crate::db::get_telegram_id;
 use crate::http_webhook::generate_notification_token;
 use crate::json_rpc::all_komari_info::get_every_one_status;
-use crate::json_rpc::connect::{connect_komari_with_update_db, update_connection};
-use crate::json_rpc::get_node_id::get_node_id_list;
-use crate::json_rpc::status::{get_node_id_by_name, make_keyboard_for_single, status_with_id};
+use crate::json_rpc::cache::{CACHE_CONFIG, cache_stats};
+use crate::json_rpc::compare::compare_nodes;
+use crate::json_rpc::connect::{
+    connect_komari_with_update_db, normalize_komari_url, update_connection,
+};
+use crate::json_rpc::get_node_id::{NodeSort, get_node_id_list};
+use crate::json_rpc::groups::{list_groups, list_tags};
+use crate::json_rpc::headers::{HeadersAction, edit_headers, validate_header};
+use crate::json_rpc::http_options::{HttpOptionsAction, edit_http_options};
+use crate::json_rpc::instances::instances;
+use crate::json_rpc::inventory::{ExportFormat, export, inventory};
+use crate::json_rpc::outdated::outdated_report;
+use crate::json_rpc::query::NODE_LIST_METHODS;
+use crate::json_rpc::resilience::RETRY_CONFIG;
+use crate::json_rpc::search::MAX_CHOICES;
+use crate::json_rpc::status::{
+    NodeSearchResult, get_node_id_by_name, make_keyboard_for_choices, make_keyboard_for_single,
+    status_with_id,
+};
+use crate::json_rpc::top::{DEFAULT_COUNT, TopMetric, top_nodes};
 use crate::json_rpc::total_status::total_status;
-use crate::utils::{Config, msg_fixer};
-use db::{DB_POOL, connect_db, create_table, delete_monitor};
+use crate::node_browser::BrowserState;
+use crate::rate_limit::{RATE_LIMIT_CONFIG, check_rate_limit, rate_limited_message};
+use crate::settings::{
+    Language, SettingsAction, can_edit, edit_settings, load_settings, settings_menu,
+};
+use crate::ssrf::SSRF_CONFIG;
+use crate::utils::{Config, ErrorType, is_admin, msg_fixer};
+use db::{
+    DB_POOL, connect_db, create_table, delete_monitor, delete_node_ids, delete_node_inventory,
+    delete_node_systems,
+};
 use log::info;
 use reqwest::Url;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::time::Duration;
 use std::{env, fs};
@@ -23,7 +57,9 @@ use teloxide::RequestError;
 use teloxide::prelude::*;
 use teloxide::sugar::bot::BotMessagesExt;
 use teloxide::sugar::request::RequestLinkPreviewExt;
-use teloxide::types::{ParseMode, ReplyParameters, True};
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ParseMode, ReplyParameters, True,
+};
 use teloxide::utils::command::parse_command;
 
 pub type MessageString = String; // With formated but did not escape
@@ -51,6 +87,11 @@ async fn main() {
         env::set_var("ADMIN_ID", config.admin_id.to_string());
     };
 
+    let _ = RATE_LIMIT_CONFIG.set(config.rate_limit);
+    let _ = CACHE_CONFIG.set(config.cache);
+    let _ = RETRY_CONFIG.set(config.retry);
+    let _ = SSRF_CONFIG.set(config.ssrf_guard);
+
     info!("Starting...");
     let bot = Bot::new(config.telegram_token);
 
@@ -68,6 +109,9 @@ async fn main() {
         },
     ));
 
+    tokio::spawn(weekly_report::run(bot.clone()));
+    tokio::spawn(node_changes::run(bot.clone()));
+
     let handler = dptree::entry()
         .branch(
             Update::filter_message().endpoint(move |bot: Bot, msg: Message| async move {
@@ -82,6 +126,11 @@ async fn main() {
                             info!("接收到来自 {:?} 命令: {:?}", msg.from, cmd);
                             cmd
                         }
+                        // 非命令消息可能是交互式连接中的回答
+                        None if msg.chat.is_private() => {
+                            let _ = onboarding::handle_text(&bot, &msg).await;
+                            return;
+                        }
                         _ => {
                             return;
                         }
@@ -111,17 +160,83 @@ async fn main() {
 
 #[derive(Debug)]
 enum Command {
-    Start,
+    Start {
+        payload: Option<String>,
+    },
     Help,
-    Connect { http_url: String },
+    Connect {
+        http_url: String,
+        /// 未解析的 `NAME=VALUE` / `NAME:VALUE` 参数
+        headers: Vec<String>,
+    },
+    ConnectInteractive,
+    Cancel,
     Disconnect,
     Update,
-    GetNodeId,
-    TotalStatus,
-    StatusId { node_id: i32 },
-    Status { node_name: String },
+    GetNodeId {
+        sort: NodeSort,
+        /// 以文本形式列出，而非分页浏览
+        text: bool,
+    },
+    TotalStatus {
+        group: Option<String>,
+    },
+    Groups,
+    Tags,
+    Top {
+        metric: TopMetric,
+        count: usize,
+        group: Option<String>,
+    },
+    StatusId {
+        node_id: Option<i32>,
+    },
+    Compare {
+        nodes: Vec<String>,
+    },
+    Inventory,
+    Outdated,
+    Export {
+        format: ExportFormat,
+    },
+    Status {
+        node_name: String,
+    },
     GenerateNotificationToken,
     AllInfo,
+    CacheStats,
+    Headers {
+        action: HeadersAction,
+    },
+    HttpOptions {
+        action: HttpOptionsAction,
+    },
+    Instances,
+    Settings {
+        action: SettingsAction,
+    },
+}
+
+impl Command {
+    // 会向 Komari 发起请求的命令需要限流
+    const fn is_rate_limited(&self) -> bool {
+        matches!(
+            self,
+            Command::Connect { .. }
+                | Command::Update
+                | Command::GetNodeId { .. }
+                | Command::TotalStatus { .. }
+                | Command::Groups
+                | Command::Tags
+                | Command::Top { .. }
+                | Command::Compare { .. }
+                | Command::Inventory
+                | Command::Outdated
+                | Command::Export { .. }
+                | Command::StatusId { .. }
+                | Command::Status { .. }
+        )
+    }
 }
 
 fn parse(text: &str, bot_name: &str) -> Option<Command> {
@@ -135,37 +250,148 @@ fn parse(text: &str, bot_name: &str) -> Option<Command> {
     };
 
     match cmd {
-        "start" => Some(Command::Start),
+        "start" => Some(Command::Start {
+            payload: args.first().map(|payload| (*payload).to_string()),
+        }),
         "help" => Some(Command::Help),
         "connect" => {
-            let http_url = args.first()?;
-
-            let http_url = if http_url.ends_with('/') {
-                http_url.trim_end_matches('/')
-            } else {
-                http_url
+            let Some(http_url) = args.first() else {
+                return Some(Command::ConnectInteractive);
             };
 
+            // 额外的 HTTP 头以 NAME=VALUE 或 NAME:VALUE 的形式跟在 URL 后
+            let headers = args.iter().skip(1).map(|arg| (*arg).to_string()).collect();
+
             Some(Command::Connect {
-                http_url: http_url.to_string(),
+                http_url: (*http_url).to_string(),
+                headers,
             })
         }
+        "cancel" => Some(Command::Cancel),
         "disconnect" => Some(Command::Disconnect),
         "update" => Some(Command::Update),
-        "get_node_id" => Some(Command::GetNodeId),
-        "total_status" => Some(Command::TotalStatus),
-        "status" => match args.first() {
-            None => Some(Command::StatusId { node_id: 1 }),
-            Some(node_name) => Some(Command::Status {
-                node_name: (*node_name).to_string(),
-            }),
-        },
+        "get_node_id" => {
+            let text = args.first() == Some(&"text");
+            let sort = match args.get(usize::from(text)) {
+                None => NodeSort::Id,
+                Some(sort) => NodeSort::from_code(sort)?,
+            };
+            Some(Command::GetNodeId { sort, text })
+        }
+        "total_status" => Some(Command::TotalStatus { group: None }),
+        "group" => {
+            if args.is_empty() {
+                Some(Command::Groups)
+            } else {
+                Some(Command::TotalStatus {
+                    group: Some(args.join(" ")),
+                })
+            }
+        }
+        "tags" => Some(Command::Tags),
+        "top" => {
+            let metric = match args.first() {
+                None => TopMetric::Cpu,
+                Some(metric) => TopMetric::from_code(&metric.to_lowercase())?,
+            };
+            // 数量可省略，其后的参数为分组名
+            let count = args.get(1).and_then(|count| count.parse::<usize>().ok());
+            let group = args
+                .get(1 + usize::from(count.is_some())..)
+                .filter(|group| !group.is_empty())
+                .map(|group| group.join(" "));
+            Some(Command::Top {
+                metric,
+                count: count.unwrap_or(DEFAULT_COUNT),
+                group,
+            })
+        }
+        "status" => {
+            if args.is_empty() {
+                Some(Command::StatusId { node_id: None })
+            } else {
+                Some(Command::Status {
+                    node_name: args.join(" "),
+                })
+            }
+        }
         "status_id" => {
-            let node_id = args.first().unwrap_or(&"1").parse::<i32>().unwrap_or(1);
+            let node_id = args.first().and_then(|node_id| node_id.parse::<i32>().ok());
             Some(Command::StatusId { node_id })
         }
+        "compare" => Some(Command::Compare {
+            nodes: args.iter().map(|node| (*node).to_string()).collect(),
+        }),
+        "inventory" => Some(Command::Inventory),
+        "outdated" => Some(Command::Outdated),
+        "export" => {
+            let format = match args.first() {
+                None => ExportFormat::Csv,
+                Some(format) => ExportFormat::from_code(&format.to_lowercase())?,
+            };
+            Some(Command::Export { format })
+        }
         "generate_notification_token" => Some(Command::GenerateNotificationToken),
         "all_info" => Some(Command::AllInfo),
+        "cache_stats" => Some(Command::CacheStats),
+        "headers" => {
+            let action = match args.first().copied() {
+                None => HeadersAction::List,
+                Some("set") => HeadersAction::Set {
+                    name: (*args.get(1)?).to_string(),
+                    value: args.get(2..)?.join(" "),
+                },
+                Some("del") => HeadersAction::Delete {
+                    name: (*args.get(1)?).to_string(),
+                },
+                Some("clear") => HeadersAction::Clear,
+                Some(_) => return None,
+            };
+            Some(Command::Headers { action })
+        }
+        "http_options" => {
+            let action = match (args.first().copied(), args.get(1).copied()) {
+                (None, _) => HttpOptionsAction::Show,
+                (Some("timeout"), Some("default")) => HttpOptionsAction::Timeout { secs: None },
+                (Some("timeout"), Some(secs)) => HttpOptionsAction::Timeout {
+                    secs: Some(secs.parse().ok()?),
+                },
+                (Some("proxy"), Some("off")) => HttpOptionsAction::Proxy { proxy: None },
+                (Some("proxy"), Some(proxy)) => HttpOptionsAction::Proxy {
+                    proxy: Some(proxy.to_string()),
+                },
+                (Some("ca"), Some("off")) => HttpOptionsAction::CaCert { pem: None },
+                // PEM 含有换行，直接从原始文本中截取
+                (Some("ca"), Some(_)) => HttpOptionsAction::CaCert {
+                    pem: Some(text[text.find("-----BEGIN")?..].trim().to_string()),
+                },
+                (Some("insecure"), Some("on")) => HttpOptionsAction::Insecure { enabled: true },
+                (Some("insecure"), Some("off")) => HttpOptionsAction::Insecure { enabled: false },
+                (Some("reset"), _) => HttpOptionsAction::Reset,
+                _ => return None,
+            };
+            Some(Command::HttpOptions { action })
+        }
+        "instances" => Some(Command::Instances),
+        "settings" => {
+            let action = match (args.first().copied(), args.get(1).copied()) {
+                (None, _) => SettingsAction::Show,
+                (Some("timezone"), Some(name)) => SettingsAction::Timezone {
+                    name: name.to_string(),
+                },
+                (Some("node"), Some("off")) => SettingsAction::DefaultNode { node_id: None },
+                (Some("node"), Some(node_id)) => SettingsAction::DefaultNode {
+                    node_id: Some(node_id.parse().ok()?),
+                },
+                (Some("agent"), Some("off")) => SettingsAction::AgentTarget { version: None },
+                (Some("agent"), Some(version)) => SettingsAction::AgentTarget {
+                    version: Some(version.to_string()),
+                },
+                (Some("reset"), _) => SettingsAction::Reset,
+                _ => return None,
+            };
+            Some(Command::Settings { action })
+        }
         _ => None,
     }
 }
@@ -183,9 +409,28 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
     let bot_clone = bot.clone();
     let chat_id = msg.chat.id;
     let reply_id = msg.id;
+    let settings = load_settings(telegram_id, chat_id).await;
+
+    if cmd.is_rate_limited()
+        && let Err(wait) = check_rate_limit(telegram_id, chat_id).await
+    {
+        let msg = bot
+            .send_message(chat_id, rate_limited_message(wait))
+            .reply_parameters(ReplyParameters::new(reply_id))
+            .await?;
+        settings.delete_later(&bot, &msg).await;
+        return Ok(());
+    }
 
     match cmd {
-        Command::Start => {
+        // 深度链接 https://t.me/BOT_NAME?start=connect
+        Command::Start { payload } if payload.as_deref() == Some("connect") => {
+            if msg.chat.is_private() {
+                onboarding::start(&bot, msg.chat.id, telegram_id).await?;
+            }
+            Ok(())
+        }
+        Command::Start { .. } => {
             bot.send_message(
                 msg.chat.id,
                 r"欢迎使用 Komari Unofficial Telegram Bot
@@ -206,14 +451,27 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
                 r"Komari Unofficial Telegram Bot
 /start, /help - 打印本菜单
 
-/connect HTTP_URL - 连接到 Komari 服务 (自动推断 WebSocket URL)
+/connect - 按步骤引导连接到 Komari 服务
+/connect HTTP_URL [NAME=VALUE ...] - 直接连接到 Komari 服务 (支持子路径及 user:pass@，可附加 HTTP 头，带凭据时仅私聊)
+/cancel - 取消进行中的连接引导
 /disconnect - 断开已保存的连接
 /update - 更新已保存的连接 (增删服务器或疑难杂症可使用)
+/headers [set NAME VALUE | del NAME | clear] - 查看或修改请求 Komari 时附加的 HTTP 头 (仅私聊)
+/http_options [timeout SECS|default | proxy URL|off | ca PEM|off | insecure on|off | reset] - 查看或修改连接选项 (仅私聊)
+/instances - 查看已连接的实例及其设置 (仅私聊)
+/settings [timezone NAME | node ID|off | agent VERSION|off | reset] - 打开设置菜单 (语言、单位、时区、卡片样式、隐藏节点、默认节点、通知会话、自动删除、数据过期阈值、客户端目标版本、每周版本报告、节点变更通知)
 
 /total_status - 获取所有节点的运行状态
-/status NODE_NAME - 获取指定节点的运行状态 (第一个包含 NODE_NAME 字符串的节点，若未传入则等同于 /status_id 1)
-/get_node_id - 获取所有节点的 ID (仅本 Bot)
+/group [NAME] - 列出所有分组，或获取指定分组内节点的运行状态
+/tags - 列出所有标签及其节点数
+/top [cpu|ram|disk|load|net|conn|traffic] [N] [GROUP] - 按指定指标列出排名前 N 的在线节点 (默认 5 个，可限定分组)
+/status KEYWORD - 搜索节点并获取其运行状态 (匹配名称、地区、分组、标签、系统及 CPU，不区分大小写，/PATTERN/ 为正则表达式，若未传入则显示默认节点)
+/get_node_id [text] [id|name|cpu|uptime] - 分页浏览所有节点 (仅本 Bot)，text 以文本形式列出节点 ID
 /status_id NODE_ID - 获取指定节点 ID (使用 /get_node_id 获取节点的 ID) 的运行状态
+/compare NODE NODE [NODE ...] - 对比多个节点 (节点 ID 或搜索关键词) 的硬件与当前负载
+/inventory - 按系统、内核、架构、虚拟化、CPU、地区及客户端版本统计节点
+/export [csv|json] - 导出所有节点的静态信息与最新状态
+/outdated - 列出客户端版本落后于目标版本 (未设置时为最常见版本) 及近期更换了系统或内核的节点
 
 /generate_notification_token - 生成通知令牌
 ",
@@ -221,76 +479,154 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .disable_link_preview(true)
                 .await?;
-            tokio::time::sleep(Duration::from_secs(5)).await;
-            bot.delete(&msg).await?;
+            settings.delete_later(&bot, &msg).await;
             Ok(())
         }
-        Command::Connect { http_url } => {
-            let url = match Url::parse(&http_url) {
-                Ok(url) => url,
-                Err(e) => {
-                    let msg = bot
-                        .send_message(msg.chat.id, format!("无效的 URL: {e}"))
-                        .reply_parameters(ReplyParameters::new(msg.id))
-                        .await?;
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await?;
-                    return Ok(());
-                }
-            };
+        Command::Connect { http_url, headers } => {
+            // 携带 HTTP 头或 user:pass@ 的消息含有凭据，不应留在聊天记录中
+            let has_credentials = !headers.is_empty()
+                || Url::parse(&http_url)
+                    .is_ok_and(|url| !url.username().is_empty() || url.password().is_some());
+            if has_credentials {
+                bot.delete(&msg).await.unwrap_or(True);
+            }
+            // 原消息可能已被删除，回复时允许不引用
+            let reply = ReplyParameters::new(msg.id).allow_sending_without_reply();
+
+            if has_credentials && !msg.chat.is_private() {
+                let msg = bot
+                    .send_message(
+                        msg.chat.id,
+                        "带有凭据的 /connect 只能用于私聊，消息已删除，请在私聊中重新连接",
+                    )
+                    .reply_parameters(reply)
+                    .await?;
+                settings.delete_later(&bot, &msg).await;
+                return Ok(());
+            }
 
-            let host = match url.host_str() {
-                None => {
+            let http_url = match normalize_komari_url(&http_url).await {
+                Ok(http_url) => http_url,
+                Err(e) => {
                     let msg = bot
-                        .send_message(msg.chat.id, "无效的 URL")
-                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .send_message(msg.chat.id, e.to_string())
+                        .reply_parameters(reply)
                         .await?;
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await?;
+                    settings.delete_later(&bot, &msg).await;
                     return Ok(());
                 }
-                Some(host) => host,
             };
 
-            let port = match url.port() {
-                None => String::new(),
-                Some(port) => format!(":{port}"),
-            };
+            let mut extra_headers = BTreeMap::new();
+            for (index, header) in headers.iter().enumerate() {
+                // 不回显参数内容，避免凭据再次出现在聊天中
+                let parsed = header
+                    .split_once([':', '='])
+                    .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+                    .ok_or(ErrorType::GeneralError {
+                        error: format!(
+                            "无法解析第 {} 个 HTTP 头，请使用 NAME=VALUE 的格式，值含有空格时请在连接后使用 /headers set NAME VALUE",
+                            index + 1
+                        ),
+                    })
+                    .and_then(|(name, value)| Ok((validate_header(name, value, settings.language)?, value)));
 
-            let http_url = format!("{}://{}{}", url.scheme(), host, port);
+                match parsed {
+                    Ok((name, value)) => {
+                        extra_headers.insert(name, value.to_string());
+                    }
+                    Err(e) => {
+                        let msg = bot
+                            .send_message(msg.chat.id, e.to_string())
+                            .reply_parameters(reply)
+                            .await?;
+                        settings.delete_later(&bot, &msg).await;
+                        return Ok(());
+                    }
+                }
+            }
 
-            match connect_komari_with_update_db(http_url, telegram_id).await {
+            match connect_komari_with_update_db(http_url, extra_headers, telegram_id, &settings)
+                .await
+            {
                 Ok(message) => {
                     bot.send_message(msg.chat.id, msg_fixer(message))
                         .parse_mode(ParseMode::MarkdownV2)
-                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .reply_parameters(reply)
                         .await?;
                 }
                 Err(e) => {
                     let msg = bot
                         .send_message(msg.chat.id, format!("获取站点信息失败: {e}"))
-                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .reply_parameters(reply)
                         .await?;
 
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await?;
+                    settings.delete_later(&bot, &msg).await;
                 }
             }
             Ok(())
         }
+        Command::ConnectInteractive => {
+            if msg.chat.is_private() {
+                onboarding::start(&bot, msg.chat.id, telegram_id).await?;
+                return Ok(());
+            }
+
+            // 群组中不便输入凭据，引导到私聊
+            let bot_name = env::var("BOT_NAME").unwrap_or_default();
+            let Ok(url) = Url::parse(&format!(
+                "https://t.me/{}?start=connect",
+                bot_name.trim_start_matches('@')
+            )) else {
+                return Ok(());
+            };
+
+            let msg = bot
+                .send_message(msg.chat.id, "请在私聊中按步骤连接 Komari")
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                    InlineKeyboardButton::url("前往私聊", url),
+                ]]))
+                .await?;
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            bot.delete(&msg).await.unwrap_or(True);
+            Ok(())
+        }
+        Command::Cancel => {
+            let text = if onboarding::cancel(msg.chat.id) {
+                "已取消连接引导"
+            } else {
+                "当前没有进行中的连接引导"
+            };
+
+            bot.send_message(msg.chat.id, text)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            Ok(())
+        }
         Command::Disconnect => {
             let db_pool = DB_POOL
                 .get()
                 .unwrap_or_else(|| panic!("数据库连接池未初始化"));
 
-            match delete_monitor(db_pool, telegram_id).await {
+            let result = match delete_monitor(db_pool, telegram_id).await {
+                Ok(()) => match delete_node_ids(db_pool, telegram_id, None).await {
+                    Ok(()) => match delete_node_inventory(db_pool, telegram_id, None).await {
+                        Ok(()) => delete_node_systems(db_pool, telegram_id, None).await,
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+
+            match result {
                 Ok(()) => {
                     let msg = bot
                         .send_message(msg.chat.id, "已取消连接到 Komari")
                         .reply_parameters(ReplyParameters::new(msg.id))
                         .await?;
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await?;
+                    settings.delete_later(&bot, &msg).await;
                     Ok(())
                 }
                 Err(e) => {
@@ -298,14 +634,13 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
                         .send_message(msg.chat.id, format!("取消连接到 Komari 失败: {e}"))
                         .reply_parameters(ReplyParameters::new(msg.id))
                         .await?;
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await?;
+                    settings.delete_later(&bot, &msg).await;
                     Ok(())
                 }
             }
         }
         Command::Update => {
-            match update_connection(telegram_id).await {
+            match update_connection(telegram_id, &settings).await {
                 Ok(message) => {
                     bot.send_message(msg.chat.id, msg_fixer(message))
                         .parse_mode(ParseMode::MarkdownV2)
@@ -317,34 +652,38 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
                         .send_message(msg.chat.id, format!("获取站点信息失败: {e}"))
                         .reply_parameters(ReplyParameters::new(msg.id))
                         .await?;
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await?;
+                    settings.delete_later(&bot, &msg).await;
                 }
             }
 
             Ok(())
         }
-        Command::GetNodeId => match get_node_id_list(telegram_id).await {
-            Ok((message, _, _)) => {
-                bot.send_message(msg.chat.id, msg_fixer(message))
-                    .parse_mode(ParseMode::MarkdownV2)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-                Ok(())
-            }
-            Err(e) => {
-                let msg = bot
-                    .send_message(msg.chat.id, format!("无法获取节点ID: {e}"))
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-                tokio::time::sleep(Duration::from_secs(5)).await;
-                bot.delete(&msg).await?;
-                Ok(())
+        Command::GetNodeId { sort, text: false } => {
+            node_browser::send(&bot, &msg, telegram_id, &settings, sort).await
+        }
+        Command::GetNodeId { sort, text: true } => {
+            match get_node_id_list(telegram_id, &NODE_LIST_METHODS, &settings, sort).await {
+                Ok((message, _, _)) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    Ok(())
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法获取节点ID: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                    Ok(())
+                }
             }
-        },
-        Command::TotalStatus => {
+        }
+        Command::TotalStatus { group } => {
             tokio::spawn(async move {
-                let message_str = match total_status(telegram_id).await {
+                let message_str = match total_status(telegram_id, group.as_deref(), &settings).await
+                {
                     Ok(message_str) => message_str.0,
                     Err(e) => {
                         let _ = bot_clone
@@ -367,26 +706,58 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
         }
         Command::Status { node_name } => {
             tokio::spawn(async move {
-                let (msg_str, all_info, node_id) =
-                    match get_node_id_by_name(telegram_id, node_name).await {
-                        Ok(msg) => msg,
-                        Err(e) => {
-                            if let Ok(msg) = bot_clone
-                                .send_message(chat_id, format!("无法解析 Komari 数据: {e}"))
-                                .reply_parameters(ReplyParameters::new(reply_id))
-                                .await
-                            {
-                                tokio::time::sleep(Duration::from_secs(5)).await;
-                                bot.delete(&msg).await.unwrap_or(True);
-                            };
-                            return;
+                let card = match get_node_id_by_name(telegram_id, node_name, &settings).await {
+                    Ok(NodeSearchResult::Single(card)) => card,
+                    Ok(NodeSearchResult::Multiple(choices)) => {
+                        let lang = settings.language;
+                        let mut text = match lang {
+                            Language::Zh => {
+                                format!("找到 {} 个匹配的节点，请选择:", choices.len())
+                            }
+                            Language::En => {
+                                format!("Found {} matching nodes, please choose:", choices.len())
+                            }
+                        };
+                        if choices.len() > MAX_CHOICES {
+                            text.push_str(&match lang {
+                                Language::Zh => {
+                                    format!("\n仅显示前 {MAX_CHOICES} 个，请使用更精确的关键词")
+                                }
+                                Language::En => format!(
+                                    "\nOnly the first {MAX_CHOICES} are shown, please use a more specific keyword"
+                                ),
+                            });
                         }
-                    };
 
-                let keyboard = make_keyboard_for_single(node_id, telegram_id, &all_info).await;
+                        let _ = bot_clone
+                            .send_message(chat_id, text)
+                            .reply_parameters(ReplyParameters::new(reply_id))
+                            .reply_markup(make_keyboard_for_choices(telegram_id, &choices))
+                            .await;
+                        return;
+                    }
+                    Err(e) => {
+                        // 未找到节点或正则表达式无效时直接提示，不是 Komari 数据的问题
+                        let text = match e {
+                            ErrorType::GeneralError { error } => error,
+                            e => format!("无法解析 Komari 数据: {e}"),
+                        };
+                        if let Ok(msg) = bot_clone
+                            .send_message(chat_id, text)
+                            .reply_parameters(ReplyParameters::new(reply_id))
+                            .await
+                        {
+                            settings.delete_later(&bot, &msg).await;
+                        };
+                        return;
+                    }
+                };
+
+                let keyboard =
+                    make_keyboard_for_single(telegram_id, &card, None, settings.language).await;
 
                 let _ = bot_clone
-                    .send_message(chat_id, msg_fixer(msg_str))
+                    .send_message(chat_id, msg_fixer(card.message))
                     .parse_mode(ParseMode::MarkdownV2)
                     .reply_parameters(ReplyParameters::new(reply_id))
                     .reply_markup(keyboard)
@@ -397,26 +768,28 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
             Ok(())
         }
         Command::StatusId { node_id } => {
+            let node_id = node_id.or(settings.default_node).unwrap_or(0);
+
             tokio::spawn(async move {
-                let (msg_str, all_info) = match status_with_id(telegram_id, node_id as u32).await {
-                    Ok(msg) => msg,
+                let card = match status_with_id(telegram_id, node_id, false, &settings).await {
+                    Ok(card) => card,
                     Err(e) => {
                         if let Ok(msg) = bot_clone
                             .send_message(chat_id, format!("无法解析 Komari 数据: {e}"))
                             .reply_parameters(ReplyParameters::new(reply_id))
                             .await
                         {
-                            tokio::time::sleep(Duration::from_secs(5)).await;
-                            bot.delete(&msg).await.unwrap_or(True);
+                            settings.delete_later(&bot, &msg).await;
                         };
                         return;
                     }
                 };
 
-                let keyboard = make_keyboard_for_single(node_id, telegram_id, &all_info).await;
+                let keyboard =
+                    make_keyboard_for_single(telegram_id, &card, None, settings.language).await;
 
                 let _ = bot_clone
-                    .send_message(chat_id, msg_fixer(msg_str))
+                    .send_message(chat_id, msg_fixer(card.message))
                     .parse_mode(ParseMode::MarkdownV2)
                     .reply_parameters(ReplyParameters::new(reply_id))
                     .reply_markup(keyboard)
@@ -429,11 +802,16 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
         Command::GenerateNotificationToken => {
             if !msg.chat.is_private() {
                 let msg = bot
-                    .send_message(msg.chat.id, "此命令只能用于私聊")
+                    .send_message(
+                        msg.chat.id,
+                        settings.language.pick(
+                            "此命令只能用于私聊",
+                            "This command can only be used in private chats",
+                        ),
+                    )
                     .reply_parameters(ReplyParameters::new(msg.id))
                     .await?;
-                tokio::time::sleep(Duration::from_secs(5)).await;
-                bot.delete(&msg).await.unwrap_or(True);
+                settings.delete_later(&bot, &msg).await;
                 return Ok(());
             }
 
@@ -449,25 +827,19 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
                         .send_message(msg.chat.id, format!("无法生成通知令牌: {e}"))
                         .reply_parameters(ReplyParameters::new(msg.id))
                         .await?;
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    bot.delete(&msg).await.unwrap_or(True);
+                    settings.delete_later(&bot, &msg).await;
                 }
             }
 
             Ok(())
         }
         Command::AllInfo => {
-            if telegram_id
-                != env::var("ADMIN_ID")
-                    .unwrap_or(String::from("5965795367"))
-                    .parse::<i64>()
-                    .unwrap_or(5965795367)
-            {
+            if !is_admin(telegram_id) {
                 return Ok(());
             }
 
             tokio::spawn(async move {
-                match get_every_one_status().await {
+                match get_every_one_status(&settings).await {
                     Ok(message) => {
                         let _ = bot_clone
                             .send_message(chat_id, msg_fixer(message))
@@ -481,23 +853,342 @@ async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
                             .reply_parameters(ReplyParameters::new(reply_id))
                             .await
                         {
-                            tokio::time::sleep(Duration::from_secs(5)).await;
-                            bot.delete(&msg).await.unwrap_or(True);
+                            settings.delete_later(&bot, &msg).await;
                         };
                     }
                 }
             });
 
+            Ok(())
+        }
+        Command::CacheStats => {
+            if !is_admin(telegram_id) {
+                return Ok(());
+            }
+
+            bot.send_message(msg.chat.id, msg_fixer(cache_stats(&settings)))
+                .parse_mode(ParseMode::MarkdownV2)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+
+            Ok(())
+        }
+        Command::Headers { action } => {
+            if !msg.chat.is_private() {
+                let msg = bot
+                    .send_message(
+                        msg.chat.id,
+                        settings.language.pick(
+                            "此命令只能用于私聊",
+                            "This command can only be used in private chats",
+                        ),
+                    )
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                settings.delete_later(&bot, &msg).await;
+                return Ok(());
+            }
+
+            match edit_headers(telegram_id, action, &settings).await {
+                Ok(message) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(
+                            msg.chat.id,
+                            format!(
+                                "{}: {}",
+                                settings
+                                    .language
+                                    .pick("无法修改 HTTP 头", "Unable to change HTTP headers"),
+                                e.localized(&settings)
+                            ),
+                        )
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::HttpOptions { action } => {
+            if !msg.chat.is_private() {
+                let msg = bot
+                    .send_message(
+                        msg.chat.id,
+                        settings.language.pick(
+                            "此命令只能用于私聊",
+                            "This command can only be used in private chats",
+                        ),
+                    )
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                settings.delete_later(&bot, &msg).await;
+                return Ok(());
+            }
+
+            match edit_http_options(telegram_id, action, &settings).await {
+                Ok(message) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(
+                            msg.chat.id,
+                            format!(
+                                "{}: {}",
+                                settings.language.pick(
+                                    "无法修改连接选项",
+                                    "Unable to change connection options"
+                                ),
+                                e.localized(&settings)
+                            ),
+                        )
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        cmd @ (Command::Groups | Command::Tags) => {
+            let result = if matches!(cmd, Command::Groups) {
+                list_groups(telegram_id, &settings).await
+            } else {
+                list_tags(telegram_id, &settings).await
+            };
+
+            match result {
+                Ok(message) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法获取节点信息: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Top {
+            metric,
+            count,
+            group,
+        } => {
+            match top_nodes(telegram_id, metric, count, group.as_deref(), &settings).await {
+                Ok((message, keyboard)) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .reply_markup(keyboard)
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法获取节点排名: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Compare { nodes } => {
+            match compare_nodes(telegram_id, &nodes, &settings).await {
+                Ok(pages) => {
+                    for page in pages {
+                        bot.send_message(msg.chat.id, msg_fixer(page))
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .reply_parameters(ReplyParameters::new(msg.id))
+                            .await?;
+                    }
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法对比节点: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Inventory => {
+            match inventory(telegram_id, &settings).await {
+                Ok(pages) => {
+                    for page in pages {
+                        bot.send_message(msg.chat.id, msg_fixer(page))
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .reply_parameters(ReplyParameters::new(msg.id))
+                            .await?;
+                    }
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法获取节点清单: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Outdated => {
+            match outdated_report(telegram_id, &settings).await {
+                Ok(message) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法获取版本报告: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Export { format } => {
+            match export(telegram_id, format, &settings).await {
+                Ok((file_name, content)) => {
+                    bot.send_document(msg.chat.id, InputFile::memory(content).file_name(file_name))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(msg.chat.id, format!("无法导出节点: {e}"))
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Instances => {
+            if !msg.chat.is_private() {
+                let msg = bot
+                    .send_message(
+                        msg.chat.id,
+                        settings.language.pick(
+                            "此命令只能用于私聊",
+                            "This command can only be used in private chats",
+                        ),
+                    )
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                settings.delete_later(&bot, &msg).await;
+                return Ok(());
+            }
+
+            match instances(telegram_id, &settings).await {
+                Ok(message) => {
+                    bot.send_message(msg.chat.id, msg_fixer(message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .disable_link_preview(true)
+                        .await?;
+                }
+                Err(e) => {
+                    let msg = bot
+                        .send_message(
+                            msg.chat.id,
+                            format!(
+                                "{}: {}",
+                                settings
+                                    .language
+                                    .pick("无法获取实例信息", "Unable to get instance information"),
+                                e.localized(&settings)
+                            ),
+                        )
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    settings.delete_later(&bot, &msg).await;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Settings { action } => {
+            if !can_edit(&bot, telegram_id, chat_id, &action).await {
+                let msg = bot
+                    .send_message(msg.chat.id, "仅群组管理员可修改群组设置")
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                settings.delete_later(&bot, &msg).await;
+                return Ok(());
+            }
+
+            if let Err(e) = edit_settings(telegram_id, chat_id, action).await {
+                let msg = bot
+                    .send_message(msg.chat.id, format!("无法修改设置: {e}"))
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                settings.delete_later(&bot, &msg).await;
+                return Ok(());
+            }
+
+            let (message, keyboard) = settings_menu(telegram_id, chat_id).await;
+            bot.send_message(msg.chat.id, message)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .reply_markup(keyboard)
+                .await?;
+
             Ok(())
         }
     }
 }
 
 async fn callback_handler(bot: Bot, q: CallbackQuery) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if let Some(ref node_id) = q.data {
-        let _ = bot.answer_callback_query(q.id.clone()).await;
+    if q.data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(settings::CALLBACK_PREFIX))
+    {
+        settings::handle_callback(&bot, &q).await?;
+        return Ok(());
+    }
+
+    if q.data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(onboarding::CALLBACK_PREFIX))
+    {
+        onboarding::handle_callback(&bot, &q).await?;
+        return Ok(());
+    }
 
-        let (callback_tg_id, node_id) = {
+    if q.data
+        .as_deref()
+        .is_some_and(|data| data.starts_with(node_browser::CALLBACK_PREFIX))
+    {
+        node_browser::handle_callback(&bot, &q).await?;
+        return Ok(());
+    }
+
+    if let Some(ref node_id) = q.data {
+        let (callback_tg_id, node_id, in_group, back) = {
             let split: Vec<String> = node_id
                 .split('-')
                 .map(std::string::ToString::to_string)
@@ -511,6 +1202,11 @@ async fn callback_handler(bot: Bot, q: CallbackQuery) -> Result<(), Box<dyn Erro
                     .get(1)
                     .ok_or("Invalid callback data".to_string())?
                     .clone(),
+                split.iter().skip(2).any(|scope| scope == "g"),
+                split
+                    .iter()
+                    .skip(2)
+                    .find_map(|scope| BrowserState::decode(scope.strip_prefix('b')?)),
             )
         };
 
@@ -522,40 +1218,55 @@ async fn callback_handler(bot: Bot, q: CallbackQuery) -> Result<(), Box<dyn Erro
             .map_err(|_| "Invalid callback data".to_string())?;
 
         if telegram_id != q.from.id.0 as i64 {
+            let _ = bot.answer_callback_query(q.id.clone()).await;
             return Ok(());
         }
 
-        let (msg_str, all_info) = match status_with_id(telegram_id, node_id as u32).await {
-            Ok(msg) => msg,
+        let chat_id = q
+            .regular_message()
+            .map_or(ChatId(telegram_id), |message| message.chat.id);
+
+        if let Err(wait) = check_rate_limit(telegram_id, chat_id).await {
+            let _ = bot
+                .answer_callback_query(q.id.clone())
+                .text(rate_limited_message(wait))
+                .await;
+            return Ok(());
+        }
+
+        let _ = bot.answer_callback_query(q.id.clone()).await;
+
+        let settings = load_settings(telegram_id, chat_id).await;
+        let card = match status_with_id(telegram_id, node_id, in_group, &settings).await {
+            Ok(card) => card,
+            // 错误信息为纯文本，其中的括号、句点等按 MarkdownV2 解析会被 Telegram 拒绝
             Err(e) => {
                 if let Some(message) = q.regular_message() {
                     let _ = bot
                         .edit_text(message, format!("无法解析 Komari 数据: {e}"))
-                        .parse_mode(ParseMode::MarkdownV2)
                         .disable_link_preview(true)
                         .await;
                 } else if let Some(id) = q.inline_message_id {
                     let _ = bot
                         .edit_message_text_inline(id, format!("无法解析 Komari 数据: {e}"))
-                        .parse_mode(ParseMode::MarkdownV2)
                         .await;
                 }
                 return Ok(());
             }
         };
 
-        let keyboard = make_keyboard_for_single(node_id, telegram_id, &all_info).await;
+        let keyboard = make_keyboard_for_single(telegram_id, &card, back, settings.language).await;
 
         if let Some(message) = q.regular_message() {
             let _ = bot
-                .edit_text(message, msg_fixer(msg_str))
+                .edit_text(message, msg_fixer(card.message))
                 .reply_markup(keyboard)
                 .parse_mode(ParseMode::MarkdownV2)
                 .disable_link_preview(true)
                 .await;
         } else if let Some(id) = q.inline_message_id {
             let _ = bot
-                .edit_message_text_inline(id, msg_fixer(msg_str))
+                .edit_message_text_inline(id, msg_fixer(card.message))
                 .reply_markup(keyboard)
                 .parse_mode(ParseMode::MarkdownV2)
                 .await;
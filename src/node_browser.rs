@@ -0,0 +1,327 @@
+use crate::TelegramId;
+use crate::json_rpc::get_node_id::{NodeSort, get_node_id_list, sort_nodes};
+use crate::json_rpc::query::STATUS_METHODS;
+use crate::rate_limit::{check_rate_limit, rate_limited_message};
+use crate::settings::{Language, Settings, load_settings};
+use crate::utils::ErrorType;
+use teloxide::prelude::*;
+use teloxide::sugar::bot::BotMessagesExt;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ReplyParameters};
+
+pub const CALLBACK_PREFIX: &str = "nodes:";
+
+const PAGE_SIZE: usize = 10;
+
+/// 按在线状态筛选节点
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeFilter {
+    #[default]
+    All,
+    Online,
+    /// 离线及从未上报的节点
+    Offline,
+}
+
+impl NodeFilter {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "all" => Some(NodeFilter::All),
+            "on" => Some(NodeFilter::Online),
+            "off" => Some(NodeFilter::Offline),
+            _ => None,
+        }
+    }
+
+    const fn code(self) -> &'static str {
+        match self {
+            NodeFilter::All => "all",
+            NodeFilter::Online => "on",
+            NodeFilter::Offline => "off",
+        }
+    }
+
+    const fn next(self) -> Self {
+        match self {
+            NodeFilter::All => NodeFilter::Online,
+            NodeFilter::Online => NodeFilter::Offline,
+            NodeFilter::Offline => NodeFilter::All,
+        }
+    }
+
+    const fn label(self, language: Language) -> &'static str {
+        match self {
+            NodeFilter::All => language.pick("全部", "All"),
+            NodeFilter::Online => language.pick("在线", "Online"),
+            NodeFilter::Offline => language.pick("离线", "Offline"),
+        }
+    }
+}
+
+const fn sort_label(sort: NodeSort, language: Language) -> &'static str {
+    match sort {
+        NodeSort::Id => "ID",
+        NodeSort::Name => language.pick("名称", "Name"),
+        NodeSort::Cpu => "CPU",
+        NodeSort::Uptime => language.pick("运行时间", "Uptime"),
+    }
+}
+
+/// 浏览器的当前页面，编码为 `{page}.{sort}.{filter}` 放入回调数据
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BrowserState {
+    pub page: usize,
+    pub sort: NodeSort,
+    pub filter: NodeFilter,
+}
+
+impl BrowserState {
+    #[must_use]
+    pub fn encode(&self) -> String {
+        format!("{}.{}.{}", self.page, self.sort.code(), self.filter.code())
+    }
+
+    #[must_use]
+    pub fn decode(state: &str) -> Option<Self> {
+        let mut parts = state.split('.');
+        let state = Self {
+            page: parts.next()?.parse().ok()?,
+            sort: NodeSort::from_code(parts.next()?)?,
+            filter: NodeFilter::from_code(parts.next()?)?,
+        };
+        parts.next().is_none().then_some(state)
+    }
+
+    fn callback(&self, telegram_id: TelegramId) -> String {
+        format!("{CALLBACK_PREFIX}{telegram_id}:{}", self.encode())
+    }
+}
+
+/// 返回节点列表的按钮
+#[must_use]
+pub fn back_button(
+    telegram_id: TelegramId,
+    state: BrowserState,
+    language: Language,
+) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(
+        language.pick("返回列表", "Back"),
+        state.callback(telegram_id),
+    )
+}
+
+/// 渲染一页节点列表
+async fn render(
+    telegram_id: TelegramId,
+    settings: &Settings,
+    mut state: BrowserState,
+) -> Result<(String, InlineKeyboardMarkup), ErrorType> {
+    let (_, all_info, node_list) =
+        get_node_id_list(telegram_id, &STATUS_METHODS, settings, NodeSort::Id).await?;
+    let lang = settings.language;
+    let fmt = settings.number_format();
+
+    let mut nodes = node_list
+        .iter()
+        .filter(|(_, _, status)| {
+            let online = status.as_ref().is_some_and(|status| status.online);
+            match state.filter {
+                NodeFilter::All => true,
+                NodeFilter::Online => online,
+                NodeFilter::Offline => !online,
+            }
+        })
+        .collect::<Vec<_>>();
+    sort_nodes(&mut nodes, state.sort, &all_info);
+
+    let pages = nodes.len().div_ceil(PAGE_SIZE).max(1);
+    state.page = state.page.min(pages - 1);
+
+    let mut keyboard = nodes
+        .iter()
+        .skip(state.page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|(node_id, node_uuid, status)| {
+            let name = all_info.common_nodes.get(node_uuid).map_or_else(
+                || lang.pick("未知节点", "Unknown node").to_string(),
+                |node| node.name.clone(),
+            );
+            let label = match status {
+                Some(status) if status.online => {
+                    format!("🟢 {name} · {}%", fmt.number(status.cpu))
+                }
+                Some(_) => format!("🔴 {name}"),
+                None => format!("⚪ {name}"),
+            };
+
+            vec![InlineKeyboardButton::callback(
+                label,
+                format!("{telegram_id}-{node_id}-b{}", state.encode()),
+            )]
+        })
+        .collect::<Vec<_>>();
+
+    let mut page_row = vec![];
+    if state.page > 0 {
+        page_row.push(InlineKeyboardButton::callback(
+            "<-",
+            BrowserState {
+                page: state.page - 1,
+                ..state
+            }
+            .callback(telegram_id),
+        ));
+    }
+    page_row.push(InlineKeyboardButton::callback(
+        format!("{} / {pages}", state.page + 1),
+        state.callback(telegram_id),
+    ));
+    if state.page + 1 < pages {
+        page_row.push(InlineKeyboardButton::callback(
+            "->",
+            BrowserState {
+                page: state.page + 1,
+                ..state
+            }
+            .callback(telegram_id),
+        ));
+    }
+    keyboard.push(page_row);
+
+    // 切换排序或筛选后回到第一页
+    keyboard.push(vec![
+        InlineKeyboardButton::callback(
+            format!(
+                "{}: {}",
+                lang.pick("排序", "Sort"),
+                sort_label(state.sort, lang)
+            ),
+            BrowserState {
+                page: 0,
+                sort: state.sort.next(),
+                ..state
+            }
+            .callback(telegram_id),
+        ),
+        InlineKeyboardButton::callback(
+            format!(
+                "{}: {}",
+                lang.pick("筛选", "Filter"),
+                state.filter.label(lang)
+            ),
+            BrowserState {
+                page: 0,
+                filter: state.filter.next(),
+                ..state
+            }
+            .callback(telegram_id),
+        ),
+    ]);
+
+    let text = match lang {
+        Language::Zh => format!(
+            "{} 节点列表，共 {} 个节点，点击节点查看状态",
+            all_info.common_public_info.sitename,
+            nodes.len()
+        ),
+        Language::En => format!(
+            "{} nodes: {} in total, tap a node to view its status",
+            all_info.common_public_info.sitename,
+            nodes.len()
+        ),
+    };
+
+    Ok((text, InlineKeyboardMarkup::new(keyboard)))
+}
+
+/// 发送节点列表的第一页
+pub async fn send(
+    bot: &Bot,
+    msg: &Message,
+    telegram_id: TelegramId,
+    settings: &Settings,
+    sort: NodeSort,
+) -> ResponseResult<()> {
+    let state = BrowserState {
+        sort,
+        ..BrowserState::default()
+    };
+
+    match render(telegram_id, settings, state).await {
+        Ok((text, keyboard)) => {
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(keyboard)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
+        Err(e) => {
+            let msg = bot
+                .send_message(
+                    msg.chat.id,
+                    format!(
+                        "{}: {}",
+                        settings
+                            .language
+                            .pick("无法获取节点列表", "Unable to get the node list"),
+                        e.localized(settings)
+                    ),
+                )
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            settings.delete_later(bot, &msg).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// 处理 `nodes:` 开头的回调，原地切换页面
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery) -> ResponseResult<()> {
+    let Some((owner, state)) = q
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(CALLBACK_PREFIX))
+        .and_then(|data| data.split_once(':'))
+    else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let telegram_id = q.from.id.0 as i64;
+    let (Some(message), Some(state), true) = (
+        q.regular_message(),
+        BrowserState::decode(state),
+        owner == telegram_id.to_string(),
+    ) else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    if let Err(wait) = check_rate_limit(telegram_id, message.chat.id).await {
+        bot.answer_callback_query(q.id.clone())
+            .text(rate_limited_message(wait))
+            .await?;
+        return Ok(());
+    }
+
+    let settings = load_settings(telegram_id, message.chat.id).await;
+    match render(telegram_id, &settings, state).await {
+        Ok((text, keyboard)) => {
+            bot.answer_callback_query(q.id.clone()).await?;
+            // 内容未变化时 Telegram 会返回错误，忽略即可
+            let _ = bot.edit_text(message, text).reply_markup(keyboard).await;
+        }
+        Err(e) => {
+            bot.answer_callback_query(q.id.clone())
+                .text(format!(
+                    "{}: {}",
+                    settings
+                        .language
+                        .pick("无法获取节点列表", "Unable to get the node list"),
+                    e.localized(&settings)
+                ))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use crate::db::{
+    DB_POOL, Monitor, NodeInventoryRow, get_all_monitors, query_node_inventory,
+    replace_node_inventory,
+};
+use crate::json_rpc::query::{CommonGetNodes, NODES, PUBLIC_INFO, get_info};
+use crate::settings::{Settings, load_settings};
+use crate::utils::{MAX_PAGE_CHARS, escaped_len, msg_fixer};
+use crate::{MessageString, TelegramId};
+use log::{error, info};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::time::Duration;
+use teloxide::RequestError;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+
+/// 轮询节点清单的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 定时比较各实例的节点清单，开启了节点变更通知的用户会收到变更记录
+pub async fn run(bot: Bot) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let Some(db) = DB_POOL.get() else {
+            continue;
+        };
+
+        let monitors = match get_all_monitors(db).await {
+            Ok(monitors) => monitors,
+            Err(e) => {
+                error!("无法查询实例列表: {e}");
+                continue;
+            }
+        };
+
+        for monitor in monitors {
+            check_monitor(&bot, db, &monitor).await;
+        }
+    }
+}
+
+fn inventory_rows(nodes: &CommonGetNodes) -> Vec<NodeInventoryRow> {
+    let mut rows = nodes
+        .iter()
+        .map(|(node_uuid, node)| NodeInventoryRow {
+            node_uuid: node_uuid.clone(),
+            name: node.name.clone(),
+            cpu_cores: node.cpu_cores,
+            mem_total: node.mem_total,
+            disk_total: node.disk_total,
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| {
+        a.name
+            .cmp(&b.name)
+            .then_with(|| a.node_uuid.cmp(&b.node_uuid))
+    });
+    rows
+}
+
+/// 比较两次的节点清单，每项变更一行
+fn diff(
+    previous: &[NodeInventoryRow],
+    current: &[NodeInventoryRow],
+    settings: &Settings,
+) -> Vec<String> {
+    let lang = settings.language;
+    let fmt = settings.number_format();
+    let previous_by_uuid = previous
+        .iter()
+        .map(|node| (node.node_uuid.as_str(), node))
+        .collect::<HashMap<_, _>>();
+    let current_by_uuid = current
+        .iter()
+        .map(|node| (node.node_uuid.as_str(), node))
+        .collect::<HashMap<_, _>>();
+
+    let mut changes = vec![];
+    for node in current {
+        let Some(old) = previous_by_uuid.get(node.node_uuid.as_str()) else {
+            changes.push(format!("+ {}: `{}`", lang.pick("新增", "Added"), node.name));
+            continue;
+        };
+
+        if old.name != node.name {
+            changes.push(format!(
+                "~ {}: `{}` -> `{}`",
+                lang.pick("重命名", "Renamed"),
+                old.name,
+                node.name
+            ));
+        }
+
+        let mut resized = vec![];
+        if old.cpu_cores != node.cpu_cores {
+            resized.push(format!(
+                "CPU `{}` -> `{}`",
+                fmt.integer(old.cpu_cores),
+                fmt.integer(node.cpu_cores)
+            ));
+        }
+        if old.mem_total != node.mem_total {
+            resized.push(format!(
+                "RAM `{}` -> `{}`",
+                fmt.bytes(old.mem_total),
+                fmt.bytes(node.mem_total)
+            ));
+        }
+        if old.disk_total != node.disk_total {
+            resized.push(format!(
+                "DISK `{}` -> `{}`",
+                fmt.bytes(old.disk_total),
+                fmt.bytes(node.disk_total)
+            ));
+        }
+        if !resized.is_empty() {
+            changes.push(format!("~ `{}`: {}", node.name, resized.join(", ")));
+        }
+    }
+
+    for node in previous {
+        if !current_by_uuid.contains_key(node.node_uuid.as_str()) {
+            changes.push(format!(
+                "- {}: `{}`",
+                lang.pick("删除", "Removed"),
+                node.name
+            ));
+        }
+    }
+
+    changes
+}
+
+/// 按行拆分变更记录，每页不超过单条消息的长度限制
+fn pages(title: &str, changes: &[String]) -> Vec<MessageString> {
+    let mut pages = vec![];
+    let mut page = String::new();
+    for change in changes {
+        if !page.is_empty() && escaped_len(&page) + escaped_len(change) + 1 > MAX_PAGE_CHARS {
+            pages.push(std::mem::take(&mut page));
+        }
+        page.push_str(change);
+        page.push('\n');
+    }
+    pages.push(page);
+
+    let total = pages.len();
+    pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, page)| {
+            if total > 1 {
+                format!("{title} ({}/{total})\n\n{}", index + 1, page.trim_end())
+            } else {
+                format!("{title}\n\n{}", page.trim_end())
+            }
+        })
+        .collect()
+}
+
+async fn check_monitor(bot: &Bot, db: &Pool<Sqlite>, monitor: &Monitor) {
+    let telegram_id = monitor.telegram_id as TelegramId;
+    let settings = load_settings(telegram_id, ChatId(telegram_id)).await;
+    if !settings.node_change_alerts {
+        return;
+    }
+
+    // 获取失败时 (如凭据失效) 不与快照比较，空列表则视为实例确实没有节点
+    let all_info = match get_info(monitor, &[PUBLIC_INFO, NODES]).await {
+        Ok(all_info) => all_info,
+        Err(e) => {
+            info!("无法获取 {telegram_id} 的节点清单: {e}");
+            return;
+        }
+    };
+
+    let current = inventory_rows(&all_info.common_nodes);
+    let previous = match query_node_inventory(db, telegram_id, &monitor.monitor_url).await {
+        Ok(previous) => previous,
+        Err(e) => {
+            error!("无法读取 {telegram_id} 的节点清单快照: {e}");
+            return;
+        }
+    };
+
+    // 首次轮询只保存快照，没有节点的实例同样保存，之后新增的节点才会被通知
+    let Some(previous) = previous else {
+        save_inventory(db, telegram_id, monitor, &current).await;
+        return;
+    };
+
+    let changes = diff(&previous, &current, &settings);
+    if changes.is_empty() {
+        return;
+    }
+
+    let title = monitor
+        .instance_name
+        .clone()
+        .unwrap_or(all_info.common_public_info.sitename);
+    let title = format!(
+        "{title} {}",
+        settings.language.pick("节点变更", "node changes")
+    );
+    let chat_id = settings.alert_chat.unwrap_or(ChatId(telegram_id));
+
+    for page in pages(&title, &changes) {
+        match bot
+            .send_message(chat_id, msg_fixer(page))
+            .parse_mode(ParseMode::MarkdownV2)
+            .await
+        {
+            Ok(_) => {}
+            // Telegram 拒绝的消息 (如 Bot 被屏蔽、会话已删除) 重试也无法发送，仍保存快照以免每次轮询重复发送
+            Err(e @ (RequestError::Api(_) | RequestError::MigrateToChatId(_))) => {
+                error!("发送 {telegram_id} 的节点变更通知失败: {e}");
+                break;
+            }
+            // 网络等临时错误保留旧快照，下次轮询重新发送
+            Err(e) => {
+                error!("发送 {telegram_id} 的节点变更通知失败: {e}");
+                return;
+            }
+        }
+    }
+
+    save_inventory(db, telegram_id, monitor, &current).await;
+}
+
+async fn save_inventory(
+    db: &Pool<Sqlite>,
+    telegram_id: TelegramId,
+    monitor: &Monitor,
+    nodes: &[NodeInventoryRow],
+) {
+    if let Err(e) = replace_node_inventory(db, telegram_id, &monitor.monitor_url, nodes).await {
+        error!("无法保存 {telegram_id} 的节点清单快照: {e}");
+    }
+}
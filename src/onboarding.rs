@@ -0,0 +1,379 @@
+use crate::TelegramId;
+use crate::db::{DB_POOL, get_telegram_id, update_instance_name};
+use crate::http_webhook::generate_notification_token;
+use crate::json_rpc::connect::{check_site, connect_komari_with_update_db, normalize_komari_url};
+use crate::json_rpc::headers::validate_header;
+use crate::rate_limit::{check_rate_limit, rate_limited_message};
+use crate::settings::{Language, load_settings};
+use crate::utils::{ErrorType, msg_fixer};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+use teloxide::sugar::bot::BotMessagesExt;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardMarkup, ParseMode, ReplyParameters, True,
+};
+
+pub const CALLBACK_PREFIX: &str = "onboard:";
+
+const DIALOGUE_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_NAME_LENGTH: usize = 32;
+
+static DIALOGUES: LazyLock<Mutex<HashMap<ChatId, Dialogue>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 交互式连接的当前步骤
+#[derive(Clone, Debug)]
+enum Step {
+    Url,
+    Credentials { http_url: String },
+    Name,
+    Notification,
+}
+
+#[derive(Debug)]
+struct Dialogue {
+    telegram_id: TelegramId,
+    step: Step,
+    updated_at: Instant,
+}
+
+fn set_step(chat_id: ChatId, telegram_id: TelegramId, step: Step) {
+    DIALOGUES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(
+            chat_id,
+            Dialogue {
+                telegram_id,
+                step,
+                updated_at: Instant::now(),
+            },
+        );
+}
+
+/// 获取该用户在此会话中的步骤，超时的会话会被丢弃
+fn current_step(chat_id: ChatId, telegram_id: TelegramId) -> Option<Step> {
+    let mut dialogues = DIALOGUES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    dialogues.retain(|_, dialogue| dialogue.updated_at.elapsed() < DIALOGUE_TIMEOUT);
+
+    dialogues
+        .get(&chat_id)
+        .filter(|dialogue| dialogue.telegram_id == telegram_id)
+        .map(|dialogue| dialogue.step.clone())
+}
+
+/// 结束会话，返回是否存在进行中的会话
+pub fn cancel(chat_id: ChatId) -> bool {
+    DIALOGUES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .remove(&chat_id)
+        .is_some()
+}
+
+fn keyboard(buttons: &[(&str, &str)]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
+        buttons
+            .iter()
+            .map(|(text, action)| {
+                InlineKeyboardButton::callback(*text, format!("{CALLBACK_PREFIX}{action}"))
+            })
+            .collect::<Vec<_>>(),
+    ])
+}
+
+fn cancel_keyboard() -> InlineKeyboardMarkup {
+    keyboard(&[("取消", "cancel")])
+}
+
+/// 开始交互式连接，仅用于私聊
+pub async fn start(bot: &Bot, chat_id: ChatId, telegram_id: TelegramId) -> ResponseResult<()> {
+    set_step(chat_id, telegram_id, Step::Url);
+
+    bot.send_message(
+        chat_id,
+        "请发送 Komari 的地址，例如 https://komari.example.com\n\n支持反向代理的子路径及 user:pass@，发送的消息会被自动删除",
+    )
+    .reply_markup(cancel_keyboard())
+    .await?;
+
+    Ok(())
+}
+
+/// 处理会话中的文本消息
+pub async fn handle_text(bot: &Bot, msg: &Message) -> ResponseResult<()> {
+    let Ok(telegram_id) = get_telegram_id(msg) else {
+        return Ok(());
+    };
+    let (Some(step), Some(text)) = (current_step(msg.chat.id, telegram_id), msg.text()) else {
+        return Ok(());
+    };
+
+    match step {
+        Step::Url => {
+            // 地址中可能含有 user:pass@，无论能否通过校验都删除
+            bot.delete(msg).await.unwrap_or(True);
+            if rate_limited(bot, msg, telegram_id).await? {
+                return Ok(());
+            }
+            on_url(bot, msg, telegram_id, text).await
+        }
+        Step::Credentials { http_url } => {
+            // 凭据不应留在聊天记录中
+            bot.delete(msg).await.unwrap_or(True);
+            if rate_limited(bot, msg, telegram_id).await? {
+                return Ok(());
+            }
+            on_credentials(bot, msg, telegram_id, http_url, text).await
+        }
+        Step::Name => on_name(bot, msg, telegram_id, text).await,
+        Step::Notification => Ok(()),
+    }
+}
+
+async fn rate_limited(bot: &Bot, msg: &Message, telegram_id: TelegramId) -> ResponseResult<bool> {
+    match check_rate_limit(telegram_id, msg.chat.id).await {
+        Ok(()) => Ok(false),
+        Err(wait) => {
+            bot.send_message(msg.chat.id, rate_limited_message(wait))
+                .reply_markup(cancel_keyboard())
+                .await?;
+            Ok(true)
+        }
+    }
+}
+
+/// 提示错误并等待用户重新输入，不会自动删除
+async fn retry(bot: &Bot, msg: &Message, e: ErrorType) -> ResponseResult<()> {
+    bot.send_message(msg.chat.id, format!("{e}\n\n请重新发送，或点击取消"))
+        .reply_markup(cancel_keyboard())
+        .await?;
+
+    Ok(())
+}
+
+async fn on_url(
+    bot: &Bot,
+    msg: &Message,
+    telegram_id: TelegramId,
+    text: &str,
+) -> ResponseResult<()> {
+    let http_url = match normalize_komari_url(text).await {
+        Ok(http_url) => http_url,
+        Err(e) => return retry(bot, msg, e).await,
+    };
+
+    let public_info = match check_site(http_url.clone(), telegram_id).await {
+        Ok(public_info) => public_info,
+        Err(e) => return retry(bot, msg, e).await,
+    };
+
+    if !public_info.private_site {
+        return connect(bot, msg, telegram_id, http_url, BTreeMap::new()).await;
+    }
+
+    set_step(msg.chat.id, telegram_id, Step::Credentials { http_url });
+
+    bot.send_message(
+        msg.chat.id,
+        msg_fixer(format!(
+            "站点 `{}` 为私有站点，需要凭据才能读取节点信息
+
+请发送 Komari 后台生成的 API Key，或按 `NAME: VALUE` 的格式逐行发送需要附加的 HTTP 头
+
+发送的消息会被自动删除",
+            public_info.sitename
+        )),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(cancel_keyboard())
+    .await?;
+
+    Ok(())
+}
+
+/// 解析凭据，单独的 API Key 会作为 Bearer 令牌发送
+fn parse_credentials(text: &str, lang: Language) -> Result<BTreeMap<String, String>, ErrorType> {
+    let text = text.trim();
+    let mut headers = BTreeMap::new();
+
+    if !text.contains(':') {
+        headers.insert(String::from("authorization"), format!("Bearer {text}"));
+        return Ok(headers);
+    }
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let (name, value) = line.split_once(':').ok_or(ErrorType::GeneralError {
+            error: format!("无法解析 HTTP 头: {line}"),
+        })?;
+        let value = value.trim().to_string();
+        let name = validate_header(name.trim(), &value, lang)?;
+        headers.insert(name, value);
+    }
+
+    Ok(headers)
+}
+
+async fn on_credentials(
+    bot: &Bot,
+    msg: &Message,
+    telegram_id: TelegramId,
+    http_url: String,
+    text: &str,
+) -> ResponseResult<()> {
+    let settings = load_settings(telegram_id, msg.chat.id).await;
+    match parse_credentials(text, settings.language) {
+        Ok(headers) => connect(bot, msg, telegram_id, http_url, headers).await,
+        Err(e) => retry(bot, msg, e).await,
+    }
+}
+
+async fn connect(
+    bot: &Bot,
+    msg: &Message,
+    telegram_id: TelegramId,
+    http_url: String,
+    headers: BTreeMap<String, String>,
+) -> ResponseResult<()> {
+    let settings = load_settings(telegram_id, msg.chat.id).await;
+    let message =
+        match connect_komari_with_update_db(http_url, headers, telegram_id, &settings).await {
+            Ok(message) => message,
+            Err(e) => return retry(bot, msg, e).await,
+        };
+
+    bot.send_message(msg.chat.id, msg_fixer(message))
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+
+    set_step(msg.chat.id, telegram_id, Step::Name);
+
+    bot.send_message(
+        msg.chat.id,
+        format!("是否为该实例命名？直接发送名称即可 (不超过 {MAX_NAME_LENGTH} 个字符)"),
+    )
+    .reply_markup(keyboard(&[("跳过", "skip_name")]))
+    .await?;
+
+    Ok(())
+}
+
+async fn on_name(
+    bot: &Bot,
+    msg: &Message,
+    telegram_id: TelegramId,
+    text: &str,
+) -> ResponseResult<()> {
+    let name = text.trim();
+    if name.is_empty() || name.contains('\n') || name.chars().count() > MAX_NAME_LENGTH {
+        bot.send_message(
+            msg.chat.id,
+            format!("名称需为单行且不超过 {MAX_NAME_LENGTH} 个字符，请重新发送"),
+        )
+        .reply_markup(keyboard(&[("跳过", "skip_name")]))
+        .await?;
+        return Ok(());
+    }
+
+    let result = match DB_POOL.get() {
+        Some(db) => update_instance_name(db, telegram_id, Some(name.to_string())).await,
+        None => Err(ErrorType::DataBaseError {
+            error: "无法获取数据库".to_string(),
+        }),
+    };
+
+    if let Err(e) = result {
+        bot.send_message(msg.chat.id, format!("无法保存名称: {e}"))
+            .reply_markup(keyboard(&[("跳过", "skip_name")]))
+            .await?;
+        return Ok(());
+    }
+
+    bot.send_message(msg.chat.id, format!("已将实例命名为 {name}"))
+        .await?;
+
+    ask_notification(bot, msg.chat.id, telegram_id).await
+}
+
+async fn ask_notification(
+    bot: &Bot,
+    chat_id: ChatId,
+    telegram_id: TelegramId,
+) -> ResponseResult<()> {
+    set_step(chat_id, telegram_id, Step::Notification);
+
+    bot.send_message(
+        chat_id,
+        "是否生成通知令牌？生成后可在 Komari 后台配置 Webhook，通过本 Bot 接收通知",
+    )
+    .reply_markup(keyboard(&[("生成通知令牌", "token"), ("完成", "done")]))
+    .await?;
+
+    Ok(())
+}
+
+/// 处理 `onboard:` 开头的回调
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery) -> ResponseResult<()> {
+    let action = q
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(CALLBACK_PREFIX))
+        .unwrap_or_default();
+    let telegram_id = q.from.id.0 as i64;
+
+    let Some(message) = q.regular_message() else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let Some(step) = current_step(message.chat.id, telegram_id) else {
+        bot.answer_callback_query(q.id.clone())
+            .text("会话已过期，请重新使用 /connect")
+            .await?;
+        return Ok(());
+    };
+
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    match (action, step) {
+        ("cancel", _) => {
+            cancel(message.chat.id);
+            bot.edit_text(message, "已取消连接").await?;
+        }
+        ("skip_name", Step::Name) => {
+            bot.edit_text(message, "已跳过命名").await?;
+            ask_notification(bot, message.chat.id, telegram_id).await?;
+        }
+        ("token", Step::Notification) => {
+            cancel(message.chat.id);
+            bot.edit_text(message, "连接完成，输入 /help 查看可用命令")
+                .await?;
+
+            match generate_notification_token(telegram_id).await {
+                Ok(token_message) => {
+                    bot.send_message(message.chat.id, msg_fixer(token_message))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .await?;
+                }
+                Err(e) => {
+                    bot.send_message(message.chat.id, format!("无法生成通知令牌: {e}"))
+                        .reply_parameters(ReplyParameters::new(message.id))
+                        .await?;
+                }
+            }
+        }
+        ("done", Step::Notification) => {
+            cancel(message.chat.id);
+            bot.edit_text(message, "连接完成，输入 /help 查看可用命令")
+                .await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use crate::TelegramId;
+use crate::db::{DB_POOL, query_monitor_by_telegram_id};
+use crate::utils::is_admin;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use teloxide::types::ChatId;
+
+pub static RATE_LIMIT_CONFIG: OnceLock<RateLimitConfig> = OnceLock::new();
+
+static BUCKETS: LazyLock<Mutex<HashMap<BucketKey, TokenBucket>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// 超过该数量后清理长时间未使用的令牌桶
+const MAX_IDLE_BUCKETS: usize = 4096;
+const BUCKET_IDLE_TIMEOUT: Duration = Duration::from_secs(3600);
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct BucketConfig {
+    /// 令牌桶容量，即允许的突发请求数
+    pub burst: u32,
+    /// 每分钟补充的令牌数
+    pub per_minute: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub user: BucketConfig,
+    pub chat: BucketConfig,
+    pub komari_host: BucketConfig,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            user: BucketConfig {
+                burst: 5,
+                per_minute: 20,
+            },
+            chat: BucketConfig {
+                burst: 10,
+                per_minute: 40,
+            },
+            komari_host: BucketConfig {
+                burst: 10,
+                per_minute: 60,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BucketKey {
+    User(TelegramId),
+    Chat(i64),
+    KomariHost(String),
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: BucketConfig) -> Self {
+        Self {
+            tokens: f64::from(config.burst),
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, config: BucketConfig, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        let refilled = elapsed * f64::from(config.per_minute) / 60.0;
+        self.tokens = (self.tokens + refilled).min(f64::from(config.burst));
+        self.last_refill = now;
+    }
+
+    fn wait_time(&self, config: BucketConfig) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        if config.per_minute == 0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) * 60.0 / f64::from(config.per_minute))
+    }
+}
+
+/// 检查并消耗用户、聊天以及 Komari 主机三个维度的令牌，任一维度不足时返回需要等待的时间
+pub async fn check_rate_limit(telegram_id: TelegramId, chat_id: ChatId) -> Result<(), Duration> {
+    let config = RATE_LIMIT_CONFIG.get_or_init(RateLimitConfig::default);
+
+    if !config.enabled || is_admin(telegram_id) {
+        return Ok(());
+    }
+
+    let mut keys = vec![
+        (BucketKey::User(telegram_id), config.user),
+        (BucketKey::Chat(chat_id.0), config.chat),
+    ];
+
+    if let Some(host) = komari_host(telegram_id).await {
+        keys.push((BucketKey::KomariHost(host), config.komari_host));
+    }
+
+    try_acquire(&keys)
+}
+
+fn try_acquire(keys: &[(BucketKey, BucketConfig)]) -> Result<(), Duration> {
+    let mut buckets = BUCKETS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let now = Instant::now();
+
+    if buckets.len() > MAX_IDLE_BUCKETS {
+        buckets.retain(|_, bucket| now.duration_since(bucket.last_refill) < BUCKET_IDLE_TIMEOUT);
+    }
+
+    let mut wait = Duration::ZERO;
+    for (key, config) in keys {
+        let bucket = buckets
+            .entry(key.clone())
+            .or_insert_with(|| TokenBucket::new(*config));
+        bucket.refill(*config, now);
+        wait = wait.max(bucket.wait_time(*config));
+    }
+
+    if !wait.is_zero() {
+        return Err(wait);
+    }
+
+    for (key, _) in keys {
+        if let Some(bucket) = buckets.get_mut(key) {
+            bucket.tokens -= 1.0;
+        }
+    }
+
+    Ok(())
+}
+
+async fn komari_host(telegram_id: TelegramId) -> Option<String> {
+    let db = DB_POOL.get()?;
+    let monitor = query_monitor_by_telegram_id(db, telegram_id).await.ok()??;
+    let url = Url::parse(&monitor.monitor_url).ok()?;
+
+    url.host_str().map(std::string::ToString::to_string)
+}
+
+#[must_use]
+pub fn rate_limited_message(wait: Duration) -> String {
+    if wait == Duration::MAX {
+        return String::from("请求过于频繁，请稍后再试");
+    }
+    format!(
+        "请求过于频繁，请在 {} 秒后重试",
+        (wait.as_secs_f64().ceil() as u64).max(1)
+    )
+}
@@ -0,0 +1,852 @@
+use crate::db::{
+    DB_POOL, SettingsRow, delete_node_inventory, delete_settings, query_settings, update_settings,
+};
+use crate::json_rpc::format::NumberFormat;
+use crate::json_rpc::outdated::parse_version;
+use crate::utils::{ErrorType, format_elapsed};
+use crate::{MessageString, TelegramId};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use log::error;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::sugar::bot::BotMessagesExt;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+pub const CALLBACK_PREFIX: &str = "settings:";
+
+const DEFAULT_DELETE_AFTER_SECS: u64 = 5;
+const DEFAULT_DECIMALS: usize = 2;
+const MAX_DECIMALS: usize = 3;
+/// 自动删除时间的可选项，0 为不删除
+const DELETE_AFTER_CHOICES: [u64; 4] = [5, 15, 60, 0];
+const DEFAULT_STALE_AFTER_SECS: u64 = 60;
+/// 数据过期阈值的可选项
+const STALE_AFTER_CHOICES: [u64; 4] = [30, 60, 300, 900];
+const TIMEZONES: [&str; 10] = [
+    "UTC",
+    "Asia/Shanghai",
+    "Asia/Hong_Kong",
+    "Asia/Tokyo",
+    "Asia/Singapore",
+    "Europe/London",
+    "Europe/Berlin",
+    "Europe/Moscow",
+    "America/New_York",
+    "America/Los_Angeles",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Language {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "zh" => Some(Language::Zh),
+            "en" => Some(Language::En),
+            _ => None,
+        }
+    }
+
+    const fn code(self) -> &'static str {
+        match self {
+            Language::Zh => "zh",
+            Language::En => "en",
+        }
+    }
+
+    /// 按语言选择文本
+    #[must_use]
+    pub const fn pick<'a>(self, zh: &'a str, en: &'a str) -> &'a str {
+        match self {
+            Language::Zh => zh,
+            Language::En => en,
+        }
+    }
+}
+
+/// 容量单位，二进制为 1024 进制 (KiB)，十进制为 1000 进制 (KB)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Binary,
+    Decimal,
+}
+
+impl Units {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "binary" => Some(Units::Binary),
+            "decimal" => Some(Units::Decimal),
+            _ => None,
+        }
+    }
+
+    const fn code(self) -> &'static str {
+        match self {
+            Units::Binary => "binary",
+            Units::Decimal => "decimal",
+        }
+    }
+}
+
+/// 网速单位，比特为 Mbps，字节为 MiB/s 或 MB/s
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Throughput {
+    #[default]
+    Bits,
+    Bytes,
+}
+
+impl Throughput {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "bits" => Some(Throughput::Bits),
+            "bytes" => Some(Throughput::Bytes),
+            _ => None,
+        }
+    }
+
+    const fn code(self) -> &'static str {
+        match self {
+            Throughput::Bits => "bits",
+            Throughput::Bytes => "bytes",
+        }
+    }
+}
+
+/// 生效的设置，由个人设置与群组设置合并而来
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub language: Language,
+    pub units: Units,
+    pub throughput: Throughput,
+    /// 小数位数
+    pub decimals: usize,
+    pub thousands_separator: bool,
+    pub timezone: Tz,
+    /// 使用精简的状态卡片
+    pub compact: bool,
+    /// `/status` 未指定节点时使用的节点 ID
+    pub default_node: Option<i32>,
+    /// 接收 Komari 通知的会话，为空时使用 Callback URL 中的 CHAT_ID
+    pub alert_chat: Option<ChatId>,
+    /// 提示消息多久后自动删除，为 0 时不删除
+    pub delete_after: Duration,
+    /// 节点超过该时长未上报即视为数据过期
+    pub stale_after: Duration,
+    /// 在节点列表中显示 Komari 后台设置为隐藏的节点
+    pub show_hidden: bool,
+    /// 客户端的目标版本，为空时以最常见的版本为准
+    pub agent_target: Option<String>,
+    /// 每周发送版本报告到通知会话
+    pub weekly_report: bool,
+    /// 节点增删、重命名或配置变更时发送通知
+    pub node_change_alerts: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            units: Units::default(),
+            throughput: Throughput::default(),
+            decimals: DEFAULT_DECIMALS,
+            thousands_separator: false,
+            timezone: Tz::UTC,
+            compact: false,
+            default_node: None,
+            alert_chat: None,
+            delete_after: Duration::from_secs(DEFAULT_DELETE_AFTER_SECS),
+            stale_after: Duration::from_secs(DEFAULT_STALE_AFTER_SECS),
+            show_hidden: false,
+            agent_target: None,
+            weekly_report: false,
+            node_change_alerts: false,
+        }
+    }
+}
+
+impl Settings {
+    fn resolve(user: Option<&SettingsRow>, chat: Option<&SettingsRow>) -> Self {
+        let mut settings = Self::default();
+
+        // 群组设置覆盖个人设置中的共享项
+        for row in [user, chat].into_iter().flatten() {
+            if let Some(language) = row.language.as_deref().and_then(Language::from_code) {
+                settings.language = language;
+            }
+            if let Some(units) = row.units.as_deref().and_then(Units::from_code) {
+                settings.units = units;
+            }
+            if let Some(throughput) = row.throughput.as_deref().and_then(Throughput::from_code) {
+                settings.throughput = throughput;
+            }
+            if let Some(decimals) = row
+                .decimals
+                .and_then(|decimals| usize::try_from(decimals).ok())
+                .filter(|decimals| *decimals <= MAX_DECIMALS)
+            {
+                settings.decimals = decimals;
+            }
+            if let Some(thousands_separator) = row.thousands_separator {
+                settings.thousands_separator = thousands_separator;
+            }
+            if let Some(timezone) = row.timezone.as_deref().and_then(|tz| tz.parse().ok()) {
+                settings.timezone = timezone;
+            }
+            if let Some(compact) = row.compact {
+                settings.compact = compact;
+            }
+            if let Some(show_hidden) = row.show_hidden {
+                settings.show_hidden = show_hidden;
+            }
+            if let Some(secs) = row
+                .delete_after_secs
+                .and_then(|secs| u64::try_from(secs).ok())
+            {
+                settings.delete_after = Duration::from_secs(secs);
+            }
+            if let Some(secs) = row
+                .stale_after_secs
+                .and_then(|secs| u64::try_from(secs).ok())
+                .filter(|secs| *secs > 0)
+            {
+                settings.stale_after = Duration::from_secs(secs);
+            }
+        }
+
+        // 默认节点、通知会话与各类报告仅属于个人
+        if let Some(user) = user {
+            settings.default_node = user.default_node.and_then(|node| i32::try_from(node).ok());
+            settings.alert_chat = user.alert_chat.map(ChatId);
+            settings.agent_target = user.agent_target.clone();
+            settings.weekly_report = user.weekly_report.unwrap_or_default();
+            settings.node_change_alerts = user.node_change_alerts.unwrap_or_default();
+        }
+
+        settings
+    }
+
+    #[must_use]
+    pub const fn number_format(&self) -> NumberFormat {
+        NumberFormat {
+            units: self.units,
+            throughput: self.throughput,
+            decimals: self.decimals,
+            thousands_separator: self.thousands_separator,
+        }
+    }
+
+    /// 将 Komari 返回的 RFC 3339 时间转换到设置的时区
+    #[must_use]
+    pub fn format_time(&self, time: &str) -> String {
+        DateTime::parse_from_rfc3339(time).map_or_else(
+            |_| time.to_string(),
+            |time| {
+                time.with_timezone(&self.timezone)
+                    .format("%Y-%m-%d %H:%M:%S %Z")
+                    .to_string()
+            },
+        )
+    }
+
+    /// 将时长格式化为最大的两个单位
+    #[must_use]
+    pub fn format_duration(&self, duration: Duration) -> String {
+        match self.language {
+            Language::Zh => format_elapsed(duration),
+            Language::En => {
+                let secs = duration.as_secs();
+                let (days, hours, minutes, seconds) = (
+                    secs / 86400,
+                    secs % 86400 / 3600,
+                    secs % 3600 / 60,
+                    secs % 60,
+                );
+
+                match (days, hours, minutes) {
+                    (0, 0, 0) => format!("{seconds}s"),
+                    (0, 0, _) => format!("{minutes}m {seconds}s"),
+                    (0, _, _) => format!("{hours}h {minutes}m"),
+                    _ => format!("{days}d {hours}h"),
+                }
+            }
+        }
+    }
+
+    /// 相对时间，如 `12 秒前` / `12s ago`
+    #[must_use]
+    pub fn format_ago(&self, elapsed: Duration) -> String {
+        match self.language {
+            Language::Zh => format!("{}前", self.format_duration(elapsed)),
+            Language::En => format!("{} ago", self.format_duration(elapsed)),
+        }
+    }
+
+    /// 按设置的时间删除提示消息
+    pub async fn delete_later(&self, bot: &Bot, msg: &Message) {
+        if self.delete_after.is_zero() {
+            return;
+        }
+
+        tokio::time::sleep(self.delete_after).await;
+        let _ = bot.delete(msg).await;
+    }
+}
+
+/// 距 RFC 3339 时间已过去的时长，无法解析时返回 `None`，晚于当前时间时视为 0
+#[must_use]
+pub fn elapsed_since(time: &str) -> Option<Duration> {
+    let time = DateTime::parse_from_rfc3339(time).ok()?;
+
+    Some(
+        Utc::now()
+            .signed_duration_since(time)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// 读取在该会话中生效的设置，读取失败时使用默认设置
+pub async fn load_settings(telegram_id: TelegramId, chat_id: ChatId) -> Settings {
+    let Some(db) = DB_POOL.get() else {
+        return Settings::default();
+    };
+
+    let user = query_settings(db, telegram_id).await;
+    let chat = if chat_id.0 == telegram_id {
+        Ok(None)
+    } else {
+        query_settings(db, chat_id.0).await
+    };
+
+    match (user, chat) {
+        (Ok(user), Ok(chat)) => Settings::resolve(user.as_ref(), chat.as_ref()),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("无法读取设置: {e}");
+            Settings::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SettingsAction {
+    Show,
+    /// 切换到下一个语言
+    Language,
+    /// 切换容量单位
+    Units,
+    /// 切换网速单位
+    Throughput,
+    /// 切换到下一个小数位数
+    Decimals,
+    /// 切换千位分隔符
+    ThousandsSeparator,
+    /// 切换精简 / 完整状态卡片
+    Compact,
+    /// 切换是否显示隐藏节点
+    ShowHidden,
+    /// 切换到下一个自动删除时间
+    DeleteAfter,
+    /// 切换到下一个数据过期阈值
+    StaleAfter,
+    Timezone {
+        name: String,
+    },
+    DefaultNode {
+        node_id: Option<i32>,
+    },
+    /// 将通知发送到当前会话，再次设置时取消
+    AlertChat,
+    AgentTarget {
+        version: Option<String>,
+    },
+    /// 切换每周版本报告
+    WeeklyReport,
+    /// 切换节点变更通知
+    NodeChangeAlerts,
+    Reset,
+}
+
+impl SettingsAction {
+    /// 是否为会话共享的设置，群组中仅管理员可修改
+    #[must_use]
+    pub const fn is_shared(&self) -> bool {
+        matches!(
+            self,
+            SettingsAction::Language
+                | SettingsAction::Units
+                | SettingsAction::Throughput
+                | SettingsAction::Decimals
+                | SettingsAction::ThousandsSeparator
+                | SettingsAction::Compact
+                | SettingsAction::ShowHidden
+                | SettingsAction::DeleteAfter
+                | SettingsAction::StaleAfter
+                | SettingsAction::Timezone { .. }
+                | SettingsAction::Reset
+        )
+    }
+}
+
+/// 检查用户能否在该会话中修改设置
+pub async fn can_edit(
+    bot: &Bot,
+    telegram_id: TelegramId,
+    chat_id: ChatId,
+    action: &SettingsAction,
+) -> bool {
+    if !action.is_shared() || chat_id.0 == telegram_id {
+        return true;
+    }
+
+    bot.get_chat_member(chat_id, UserId(telegram_id as u64))
+        .await
+        .is_ok_and(|member| member.is_privileged())
+}
+
+pub async fn edit_settings(
+    telegram_id: TelegramId,
+    chat_id: ChatId,
+    action: SettingsAction,
+) -> Result<(), ErrorType> {
+    let db = DB_POOL.get().ok_or(ErrorType::DataBaseError {
+        error: "无法获取数据库".to_string(),
+    })?;
+
+    match action {
+        SettingsAction::Show => return Ok(()),
+        // 私聊中会同时清除个人设置，节点变更通知随之关闭，快照一并清除
+        SettingsAction::Reset => {
+            if chat_id.0 == telegram_id {
+                delete_node_inventory(db, telegram_id, None).await?;
+            }
+            return delete_settings(db, chat_id.0).await;
+        }
+        _ => {}
+    }
+
+    let id = if action.is_shared() {
+        chat_id.0
+    } else {
+        telegram_id
+    };
+    let current = load_settings(telegram_id, chat_id).await;
+    let mut row = query_settings(db, id).await?.unwrap_or(SettingsRow {
+        id,
+        ..SettingsRow::default()
+    });
+
+    match action {
+        SettingsAction::Show | SettingsAction::Reset => {}
+        SettingsAction::Language => {
+            let language = match current.language {
+                Language::Zh => Language::En,
+                Language::En => Language::Zh,
+            };
+            row.language = Some(language.code().to_string());
+        }
+        SettingsAction::Units => {
+            let units = match current.units {
+                Units::Binary => Units::Decimal,
+                Units::Decimal => Units::Binary,
+            };
+            row.units = Some(units.code().to_string());
+        }
+        SettingsAction::Throughput => {
+            let throughput = match current.throughput {
+                Throughput::Bits => Throughput::Bytes,
+                Throughput::Bytes => Throughput::Bits,
+            };
+            row.throughput = Some(throughput.code().to_string());
+        }
+        SettingsAction::Decimals => {
+            let decimals = (current.decimals + 1) % (MAX_DECIMALS + 1);
+            row.decimals = i64::try_from(decimals).ok();
+        }
+        SettingsAction::ThousandsSeparator => {
+            row.thousands_separator = Some(!current.thousands_separator);
+        }
+        SettingsAction::Compact => row.compact = Some(!current.compact),
+        SettingsAction::ShowHidden => row.show_hidden = Some(!current.show_hidden),
+        SettingsAction::DeleteAfter => {
+            let secs = current.delete_after.as_secs();
+            let next = DELETE_AFTER_CHOICES
+                .iter()
+                .position(|choice| *choice == secs)
+                .map_or(DEFAULT_DELETE_AFTER_SECS, |index| {
+                    DELETE_AFTER_CHOICES[(index + 1) % DELETE_AFTER_CHOICES.len()]
+                });
+            row.delete_after_secs = i64::try_from(next).ok();
+        }
+        SettingsAction::StaleAfter => {
+            let secs = current.stale_after.as_secs();
+            let next = STALE_AFTER_CHOICES
+                .iter()
+                .position(|choice| *choice == secs)
+                .map_or(DEFAULT_STALE_AFTER_SECS, |index| {
+                    STALE_AFTER_CHOICES[(index + 1) % STALE_AFTER_CHOICES.len()]
+                });
+            row.stale_after_secs = i64::try_from(next).ok();
+        }
+        SettingsAction::Timezone { name } => {
+            let timezone = name.parse::<Tz>().map_err(|_| ErrorType::GeneralError {
+                error: format!("未知的时区: {name}，请使用 IANA 时区名称，如 Asia/Shanghai"),
+            })?;
+            row.timezone = Some(timezone.name().to_string());
+        }
+        SettingsAction::DefaultNode { node_id } => {
+            if node_id.is_some_and(|node_id| node_id < 1) {
+                return Err(ErrorType::GeneralError {
+                    error: String::from("节点 ID 需大于 0"),
+                });
+            }
+            row.default_node = node_id.map(i64::from);
+        }
+        SettingsAction::AlertChat => {
+            row.alert_chat = if current.alert_chat == Some(chat_id) {
+                None
+            } else {
+                Some(chat_id.0)
+            };
+        }
+        SettingsAction::AgentTarget { version } => {
+            if let Some(version) = &version
+                && parse_version(version).is_none()
+            {
+                return Err(ErrorType::GeneralError {
+                    error: format!("无法识别的版本号: {version}"),
+                });
+            }
+            row.agent_target = version;
+        }
+        SettingsAction::WeeklyReport => row.weekly_report = Some(!current.weekly_report),
+        SettingsAction::NodeChangeAlerts => {
+            // 关闭后快照不再更新，清除以免再次开启时与过期的快照比较
+            if current.node_change_alerts {
+                delete_node_inventory(db, telegram_id, None).await?;
+            }
+            row.node_change_alerts = Some(!current.node_change_alerts);
+        }
+    }
+
+    update_settings(db, &row).await
+}
+
+fn callback(telegram_id: TelegramId, action: &str) -> String {
+    format!("{CALLBACK_PREFIX}{telegram_id}:{action}")
+}
+
+/// 设置菜单的文本与按钮
+pub async fn settings_menu(
+    telegram_id: TelegramId,
+    chat_id: ChatId,
+) -> (MessageString, InlineKeyboardMarkup) {
+    let settings = load_settings(telegram_id, chat_id).await;
+    let lang = settings.language;
+
+    let language = lang.pick("中文", "English");
+    let units = match settings.units {
+        Units::Binary => lang.pick("二进制 (KiB)", "Binary (KiB)"),
+        Units::Decimal => lang.pick("十进制 (kB)", "SI (kB)"),
+    };
+    let throughput = match settings.throughput {
+        Throughput::Bits => "Mbps",
+        Throughput::Bytes => match settings.units {
+            Units::Binary => "MiB/s",
+            Units::Decimal => "MB/s",
+        },
+    };
+    let thousands_separator = if settings.thousands_separator {
+        lang.pick("开启", "On")
+    } else {
+        lang.pick("关闭", "Off")
+    };
+    let card = if settings.compact {
+        lang.pick("精简", "Compact")
+    } else {
+        lang.pick("完整", "Full")
+    };
+    let hidden_nodes = if settings.show_hidden {
+        lang.pick("显示", "Shown")
+    } else {
+        lang.pick("不显示", "Hidden")
+    };
+    // 未设置时使用列表中的第一个节点，不一定是 ID 为 1 的节点
+    let default_node = settings.default_node.map_or_else(
+        || lang.pick("第一个节点", "First listed node").to_string(),
+        |node| node.to_string(),
+    );
+    let alert_chat = match settings.alert_chat {
+        None => lang
+            .pick("Callback URL 中的会话", "Chat in callback URL")
+            .to_string(),
+        Some(alert_chat) if alert_chat == chat_id => lang.pick("当前会话", "This chat").to_string(),
+        Some(alert_chat) => alert_chat.0.to_string(),
+    };
+    let delete_after = if settings.delete_after.is_zero() {
+        lang.pick("不删除", "Never").to_string()
+    } else {
+        format!("{}s", settings.delete_after.as_secs())
+    };
+    let stale_after = settings.format_duration(settings.stale_after);
+    let agent_target = settings
+        .agent_target
+        .clone()
+        .unwrap_or_else(|| lang.pick("最常见的版本", "Most common version").to_string());
+    let weekly_report = if settings.weekly_report {
+        lang.pick("开启", "On")
+    } else {
+        lang.pick("关闭", "Off")
+    };
+    let node_change_alerts = if settings.node_change_alerts {
+        lang.pick("开启", "On")
+    } else {
+        lang.pick("关闭", "Off")
+    };
+
+    let scope = if chat_id.0 == telegram_id {
+        lang.pick("个人设置", "Personal settings")
+    } else {
+        lang.pick(
+            "群组设置 (默认节点、通知会话、版本报告与节点变更通知为个人设置)",
+            "Group settings (default node, alert chat and reports are personal)",
+        )
+    };
+
+    let message = format!(
+        "{scope}
+
+{language_label}: {language}
+{units_label}: {units}
+{throughput_label}: {throughput}
+{decimals_label}: {decimals}
+{thousands_separator_label}: {thousands_separator}
+{timezone_label}: {timezone}
+{card_label}: {card}
+{hidden_nodes_label}: {hidden_nodes}
+{default_node_label}: {default_node}
+{alert_chat_label}: {alert_chat}
+{delete_after_label}: {delete_after}
+{stale_after_label}: {stale_after}
+{agent_target_label}: {agent_target}
+{weekly_report_label}: {weekly_report}
+{node_change_alerts_label}: {node_change_alerts}",
+        language_label = lang.pick("语言", "Language"),
+        units_label = lang.pick("单位", "Units"),
+        throughput_label = lang.pick("网速单位", "Throughput"),
+        decimals_label = lang.pick("小数位数", "Decimals"),
+        decimals = settings.decimals,
+        thousands_separator_label = lang.pick("千位分隔符", "Thousands separator"),
+        timezone_label = lang.pick("时区", "Timezone"),
+        timezone = settings.timezone.name(),
+        card_label = lang.pick("状态卡片", "Status card"),
+        hidden_nodes_label = lang.pick("隐藏节点", "Hidden nodes"),
+        default_node_label = lang.pick("默认节点", "Default node"),
+        alert_chat_label = lang.pick("通知会话", "Alert chat"),
+        delete_after_label = lang.pick("提示自动删除", "Auto-delete"),
+        stale_after_label = lang.pick("数据过期阈值", "Stale after"),
+        agent_target_label = lang.pick("客户端目标版本", "Agent target version"),
+        weekly_report_label = lang.pick("每周版本报告", "Weekly version report"),
+        node_change_alerts_label = lang.pick("节点变更通知", "Node change alerts"),
+    );
+
+    let keyboard = InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback(
+                format!("{}: {language}", lang.pick("语言", "Language")),
+                callback(telegram_id, "lang"),
+            ),
+            InlineKeyboardButton::callback(
+                format!("{}: {units}", lang.pick("单位", "Units")),
+                callback(telegram_id, "units"),
+            ),
+        ],
+        vec![
+            InlineKeyboardButton::callback(
+                format!("{}: {throughput}", lang.pick("网速", "Speed")),
+                callback(telegram_id, "throughput"),
+            ),
+            InlineKeyboardButton::callback(
+                format!("{}: {}", lang.pick("小数", "Decimals"), settings.decimals),
+                callback(telegram_id, "decimals"),
+            ),
+            InlineKeyboardButton::callback(
+                format!("1,000: {thousands_separator}"),
+                callback(telegram_id, "separator"),
+            ),
+        ],
+        vec![
+            InlineKeyboardButton::callback(
+                format!(
+                    "{}: {}",
+                    lang.pick("时区", "Timezone"),
+                    settings.timezone.name()
+                ),
+                callback(telegram_id, "tz"),
+            ),
+            InlineKeyboardButton::callback(
+                format!("{}: {card}", lang.pick("卡片", "Card")),
+                callback(telegram_id, "compact"),
+            ),
+            InlineKeyboardButton::callback(
+                format!("{}: {hidden_nodes}", lang.pick("隐藏节点", "Hidden")),
+                callback(telegram_id, "hidden"),
+            ),
+        ],
+        vec![
+            InlineKeyboardButton::callback("-", callback(telegram_id, "node-")),
+            InlineKeyboardButton::callback(
+                format!("{}: {default_node}", lang.pick("默认节点", "Default node")),
+                callback(telegram_id, "menu"),
+            ),
+            InlineKeyboardButton::callback("+", callback(telegram_id, "node+")),
+        ],
+        vec![
+            InlineKeyboardButton::callback(
+                format!("{}: {delete_after}", lang.pick("自动删除", "Auto-delete")),
+                callback(telegram_id, "ttl"),
+            ),
+            InlineKeyboardButton::callback(
+                format!("{}: {stale_after}", lang.pick("过期", "Stale")),
+                callback(telegram_id, "stale"),
+            ),
+        ],
+        vec![
+            InlineKeyboardButton::callback(
+                lang.pick("通知发送到此会话", "Send alerts here"),
+                callback(telegram_id, "alert"),
+            ),
+            InlineKeyboardButton::callback(
+                format!(
+                    "{}: {weekly_report}",
+                    lang.pick("每周报告", "Weekly report")
+                ),
+                callback(telegram_id, "weekly"),
+            ),
+        ],
+        vec![InlineKeyboardButton::callback(
+            format!(
+                "{}: {node_change_alerts}",
+                lang.pick("节点变更通知", "Node change alerts")
+            ),
+            callback(telegram_id, "changes"),
+        )],
+        vec![InlineKeyboardButton::callback(
+            lang.pick("恢复默认", "Reset"),
+            callback(telegram_id, "reset"),
+        )],
+    ]);
+
+    (message, keyboard)
+}
+
+fn timezone_keyboard(telegram_id: TelegramId, language: Language) -> InlineKeyboardMarkup {
+    let mut keyboard = TIMEZONES
+        .chunks(2)
+        .map(|row| {
+            row.iter()
+                .map(|tz| {
+                    InlineKeyboardButton::callback(*tz, callback(telegram_id, &format!("tz={tz}")))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        language.pick("返回", "Back"),
+        callback(telegram_id, "menu"),
+    )]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// 处理 `settings:` 开头的回调，原地编辑菜单消息
+pub async fn handle_callback(bot: &Bot, q: &CallbackQuery) -> ResponseResult<()> {
+    let Some((owner, code)) = q
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(CALLBACK_PREFIX))
+        .and_then(|data| data.split_once(':'))
+    else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+
+    let telegram_id = q.from.id.0 as i64;
+    let (Some(message), true) = (q.regular_message(), owner == telegram_id.to_string()) else {
+        bot.answer_callback_query(q.id.clone()).await?;
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let current = load_settings(telegram_id, chat_id).await;
+
+    if code == "tz" {
+        bot.answer_callback_query(q.id.clone()).await?;
+        bot.edit_reply_markup(message)
+            .reply_markup(timezone_keyboard(telegram_id, current.language))
+            .await?;
+        return Ok(());
+    }
+
+    let action = match code {
+        "lang" => SettingsAction::Language,
+        "units" => SettingsAction::Units,
+        "throughput" => SettingsAction::Throughput,
+        "decimals" => SettingsAction::Decimals,
+        "separator" => SettingsAction::ThousandsSeparator,
+        "compact" => SettingsAction::Compact,
+        "hidden" => SettingsAction::ShowHidden,
+        "ttl" => SettingsAction::DeleteAfter,
+        "stale" => SettingsAction::StaleAfter,
+        "alert" => SettingsAction::AlertChat,
+        "weekly" => SettingsAction::WeeklyReport,
+        "changes" => SettingsAction::NodeChangeAlerts,
+        "reset" => SettingsAction::Reset,
+        "node-" => SettingsAction::DefaultNode {
+            node_id: current
+                .default_node
+                .map(|node| node - 1)
+                .filter(|node| *node > 0),
+        },
+        "node+" => SettingsAction::DefaultNode {
+            node_id: Some(current.default_node.map_or(1, |node| node + 1)),
+        },
+        code => match code.strip_prefix("tz=") {
+            Some(name) => SettingsAction::Timezone {
+                name: name.to_string(),
+            },
+            None => SettingsAction::Show,
+        },
+    };
+
+    if !can_edit(bot, telegram_id, chat_id, &action).await {
+        bot.answer_callback_query(q.id.clone())
+            .text(current.language.pick(
+                "仅群组管理员可修改群组设置",
+                "Only group admins can change group settings",
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    if let Err(e) = edit_settings(telegram_id, chat_id, action).await {
+        bot.answer_callback_query(q.id.clone())
+            .text(e.to_string())
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let (text, keyboard) = settings_menu(telegram_id, chat_id).await;
+    // 内容未变化时 Telegram 会返回错误，忽略即可
+    let _ = bot.edit_text(message, text).reply_markup(keyboard).await;
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use crate::utils::ErrorType;
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::OnceLock;
+
+pub static SSRF_CONFIG: OnceLock<SsrfConfig> = OnceLock::new();
+
+const MAX_REDIRECTS: usize = 10;
+
+/// 公开部署时限制 Bot 可访问的地址，防止通过 /connect 访问内网服务
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SsrfConfig {
+    pub enabled: bool,
+    /// 允许访问的主机名、IP 或 CIDR，如 `komari.lan`、`10.0.0.0/8`
+    pub allowlist: Vec<String>,
+}
+
+fn config() -> &'static SsrfConfig {
+    SSRF_CONFIG.get_or_init(SsrfConfig::default)
+}
+
+fn parse_cidr(entry: &str) -> Option<(IpAddr, u32)> {
+    match entry.split_once('/') {
+        Some((ip, prefix)) => Some((ip.parse().ok()?, prefix.parse().ok()?)),
+        None => {
+            let ip: IpAddr = entry.parse().ok()?;
+            Some((ip, if ip.is_ipv4() { 32 } else { 128 }))
+        }
+    }
+}
+
+fn cidr_contains(network: IpAddr, prefix: u32, ip: IpAddr) -> bool {
+    match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix.min(32)).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix.min(128)).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+fn host_allowlisted(host: &str) -> bool {
+    config()
+        .allowlist
+        .iter()
+        .any(|entry| entry.eq_ignore_ascii_case(host))
+}
+
+fn ip_allowlisted(ip: IpAddr) -> bool {
+    config()
+        .allowlist
+        .iter()
+        .filter_map(|entry| parse_cidr(entry))
+        .any(|(network, prefix)| cidr_contains(network, prefix, ip))
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        // 0.0.0.0/8，Linux 上 0.0.0.0 等同于本机
+        || ip.octets()[0] == 0
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        // 100.64.0.0/10 CGNAT
+        || cidr_contains(
+            IpAddr::V4(Ipv4Addr::new(100, 64, 0, 0)),
+            10,
+            IpAddr::V4(ip),
+        )
+        // 192.0.0.0/24 IETF 协议分配
+        || cidr_contains(
+            IpAddr::V4(Ipv4Addr::new(192, 0, 0, 0)),
+            24,
+            IpAddr::V4(ip),
+        )
+        // 198.18.0.0/15 基准测试
+        || cidr_contains(
+            IpAddr::V4(Ipv4Addr::new(198, 18, 0, 0)),
+            15,
+            IpAddr::V4(ip),
+        )
+        // 240.0.0.0/4 保留地址
+        || cidr_contains(
+            IpAddr::V4(Ipv4Addr::new(240, 0, 0, 0)),
+            4,
+            IpAddr::V4(ip),
+        )
+}
+
+/// 内嵌 IPv4 地址的 IPv6 地址，经转换后可访问对应的 IPv4 地址
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    // 低 32 位
+    let tail = Ipv4Addr::from(ip.to_bits() as u32);
+
+    match ip.segments() {
+        // ::ffff:a.b.c.d IPv4 映射地址
+        [0, 0, 0, 0, 0, 0xffff, _, _] => Some(tail),
+        // 64:ff9b::a.b.c.d NAT64
+        [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(tail),
+        // 2002:aabb:ccdd::/48 6to4
+        [0x2002, high, low, ..] => Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low))),
+        // ::a.b.c.d IPv4 兼容地址，:: 与 ::1 另行判断
+        [0, 0, 0, 0, 0, 0, _, _] if !ip.is_unspecified() && !ip.is_loopback() => Some(tail),
+        _ => None,
+    }
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    if let Some(ip) = embedded_v4(ip) {
+        return is_internal_v4(ip);
+    }
+
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+}
+
+/// 该 IP 是否允许访问
+#[must_use]
+pub fn is_allowed_ip(ip: IpAddr) -> bool {
+    if !config().enabled || ip_allowlisted(ip) {
+        return true;
+    }
+
+    match ip {
+        IpAddr::V4(ip) => !is_internal_v4(ip),
+        IpAddr::V6(ip) => !is_internal_v6(ip),
+    }
+}
+
+/// 解析并检查 URL 指向的地址，任一解析结果为内网地址即拒绝
+pub async fn check_url(url: &Url) -> Result<(), ErrorType> {
+    if !config().enabled {
+        return Ok(());
+    }
+
+    let Some(host) = url.host_str() else {
+        return Err(ErrorType::GeneralError {
+            error: String::from("无效的 URL"),
+        });
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    if host_allowlisted(host) {
+        return Ok(());
+    }
+
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| ErrorType::RequestError {
+            error: format!("无法解析域名 {host}: {e}"),
+        })?
+        .collect::<Vec<_>>();
+
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_allowed_ip(addr.ip())) {
+        return Err(ErrorType::ForbiddenTarget {
+            host: host.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// 检查直接使用 IP 的 URL，这类地址不会经过 [`GuardedResolver`]
+pub fn check_ip_literal(url: &Url) -> Result<(), ErrorType> {
+    let Some(host) = url.host_str() else {
+        return Err(ErrorType::GeneralError {
+            error: String::from("无效的 URL"),
+        });
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    match host.parse::<IpAddr>() {
+        Ok(ip) if !is_allowed_ip(ip) => Err(ErrorType::ForbiddenTarget {
+            host: host.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// 在连接时过滤内网地址的 DNS 解析器，可防止 DNS 重绑定
+pub struct GuardedResolver;
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs = tokio::net::lookup_host((host.as_str(), 0)).await?;
+
+            let allowed = if host_allowlisted(&host) {
+                addrs.collect::<Vec<SocketAddr>>()
+            } else {
+                addrs.filter(|addr| is_allowed_ip(addr.ip())).collect()
+            };
+
+            if allowed.is_empty() {
+                return Err(ErrorType::ForbiddenTarget { host }.to_string().into());
+            }
+
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 跟随重定向前检查目标，域名由 [`GuardedResolver`] 在连接时检查
+#[must_use]
+pub fn redirect_policy() -> Policy {
+    Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("重定向次数过多");
+        }
+
+        match check_ip_literal(attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e.to_string()),
+        }
+    })
+}
+
+#[must_use]
+pub fn enabled() -> bool {
+    config().enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_addresses() {
+        let cases: [(&str, bool); 28] = [
+            ("127.0.0.1", true),
+            ("10.1.2.3", true),
+            ("172.16.0.1", true),
+            ("192.168.1.1", true),
+            ("169.254.169.254", true),
+            ("100.64.0.1", true),
+            ("0.0.0.0", true),
+            ("0.1.2.3", true),
+            ("255.255.255.255", true),
+            ("192.0.0.8", true),
+            ("192.0.1.1", false),
+            ("198.18.0.1", true),
+            ("198.19.255.254", true),
+            ("198.20.0.1", false),
+            ("240.0.0.1", true),
+            ("8.8.8.8", false),
+            ("::", true),
+            ("::1", true),
+            ("fc00::1", true),
+            ("fe80::1", true),
+            ("::ffff:127.0.0.1", true),
+            ("::ffff:8.8.8.8", false),
+            ("64:ff9b::7f00:1", true),
+            ("64:ff9b::808:808", false),
+            ("2002:7f00:1::", true),
+            ("2002:808:808::", false),
+            ("::10.0.0.1", true),
+            ("2001:4860:4860::8888", false),
+        ];
+
+        for (ip, internal) in cases {
+            let result = match ip.parse::<IpAddr>().unwrap() {
+                IpAddr::V4(ip) => is_internal_v4(ip),
+                IpAddr::V6(ip) => is_internal_v6(ip),
+            };
+            assert_eq!(result, internal, "{ip}");
+        }
+    }
+}
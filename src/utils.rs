@@ -1,26 +1,41 @@
-use crate::MessageString;
+use crate::json_rpc::cache::CacheConfig;
+use crate::json_rpc::client::METHOD_NOT_FOUND;
+use crate::json_rpc::resilience::RetryConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::settings::{Language, Settings};
+use crate::ssrf::SsrfConfig;
+use crate::{MessageString, TelegramId};
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::fmt::Formatter;
+use std::time::{Duration, SystemTime};
+
+/// MarkdownV2 中需要转义的字符
+const ESCAPED_CHARS: [char; 17] = [
+    '.', '-', '|', '(', ')', '#', '+', '=', '{', '}', '[', ']', '_', '>', '<', '&', '!',
+];
+
+/// 分页消息中单页内容经 [`msg_fixer`] 转义后的最大字符数，为标题留出余量，整条消息低于 Telegram 的 4096 字符限制
+pub const MAX_PAGE_CHARS: usize = 3500;
 
 #[must_use]
 pub fn msg_fixer(msg: MessageString) -> String {
-    msg.replace('.', r"\.")
-        .replace('-', r"\-")
-        .replace('|', r"\|")
-        .replace('(', r"\(")
-        .replace(')', r"\)")
-        .replace('#', r"\#")
-        .replace('+', r"\+")
-        .replace('=', r"\=")
-        .replace('{', r"\{")
-        .replace('}', r"\}")
-        .replace('[', r"\[")
-        .replace(']', r"\]")
-        .replace('_', r"\_")
-        .replace('>', r"\>")
-        .replace('<', r"\<")
-        .replace('&', r"\&")
-        .replace('!', r"\!")
+    let mut fixed = String::with_capacity(msg.len());
+    for c in msg.chars() {
+        if ESCAPED_CHARS.contains(&c) {
+            fixed.push('\\');
+        }
+        fixed.push(c);
+    }
+    fixed
+}
+
+/// 经 [`msg_fixer`] 转义后的字符数
+#[must_use]
+pub fn escaped_len(msg: &str) -> usize {
+    msg.chars()
+        .map(|c| if ESCAPED_CHARS.contains(&c) { 2 } else { 1 })
+        .sum()
 }
 
 fn mask_url(text: &str) -> String {
@@ -46,52 +61,166 @@ pub struct Config {
     pub callback_http_url: String,
     pub log_level: String,
     pub admin_id: i64,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub ssrf_guard: SsrfConfig,
+}
+
+#[must_use]
+pub fn is_admin(telegram_id: TelegramId) -> bool {
+    telegram_id
+        == env::var("ADMIN_ID")
+            .unwrap_or(String::from("5965795367"))
+            .parse::<i64>()
+            .unwrap_or(5965795367)
+}
+
+/// 将时长格式化为最大的两个单位，如 `3 分钟 12 秒`
+#[must_use]
+pub fn format_elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds} 秒"),
+        (0, 0, _) => format!("{minutes} 分钟 {seconds} 秒"),
+        (0, _, _) => format!("{hours} 小时 {minutes} 分钟"),
+        _ => format!("{days} 天 {hours} 小时"),
+    }
 }
 
 pub type ErrorString = String;
 
 pub enum ErrorType {
     UserNotConnected,
-    DataBaseError { error: ErrorString },
-    EnvironmentVariablesUndefined { var: String },
-    UnableToCreateReqwestClient { error: ErrorString },
-    RequestError { error: ErrorString },
-    JsonParseError { error: ErrorString },
+    DataBaseError {
+        error: ErrorString,
+    },
+    EnvironmentVariablesUndefined {
+        var: String,
+    },
+    UnableToCreateReqwestClient {
+        error: ErrorString,
+    },
+    RequestError {
+        error: ErrorString,
+    },
+    JsonParseError {
+        error: ErrorString,
+    },
+    JsonRpcError {
+        method: String,
+        code: i64,
+        message: String,
+        data: Option<String>,
+    },
+    InstanceUnreachable {
+        since: SystemTime,
+    },
+    ForbiddenTarget {
+        host: String,
+    },
     UnableToFindServerByUUID,
-    GeneralError { error: ErrorString },
+    GeneralError {
+        error: ErrorString,
+    },
 }
 
-impl std::fmt::Display for ErrorType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl ErrorType {
+    /// 按设置中的语言描述错误
+    #[must_use]
+    pub fn localized(&self, settings: &Settings) -> String {
+        let lang = settings.language;
         match self {
-            ErrorType::UserNotConnected => {
-                write!(f, "未连接 Komari，请使用 /connect [KOMARI_HTTP_URL] 连接")
-            }
+            ErrorType::UserNotConnected => lang
+                .pick(
+                    "未连接 Komari，请使用 /connect [KOMARI_HTTP_URL] 连接",
+                    "Not connected to Komari, use /connect [KOMARI_HTTP_URL] to connect",
+                )
+                .to_string(),
             ErrorType::DataBaseError { error } => {
-                write!(f, "数据库错误: {}", error)
+                format!("{}: {error}", lang.pick("数据库错误", "Database error"))
             }
-            ErrorType::EnvironmentVariablesUndefined { var } => {
-                write!(f, "环境变量未定义: {}", var)
-            }
-            ErrorType::UnableToCreateReqwestClient { error } => {
-                write!(f, "无法创建 Reqwest 客户端: {}", error)
+            ErrorType::EnvironmentVariablesUndefined { var } => format!(
+                "{}: {var}",
+                lang.pick("环境变量未定义", "Environment variable not defined")
+            ),
+            ErrorType::UnableToCreateReqwestClient { error } => format!(
+                "{}: {error}",
+                lang.pick("无法创建 Reqwest 客户端", "Unable to create the HTTP client")
+            ),
+            ErrorType::RequestError { error } => format!(
+                "{}: {}",
+                lang.pick("请求错误", "Request error"),
+                mask_url(error)
+            ),
+            ErrorType::JsonParseError { error } => {
+                format!("{}: {error}", lang.pick("JSON 解析错误", "JSON parse error"))
             }
-            ErrorType::RequestError { error } => {
-                let masked_error = mask_url(error);
-                write!(f, "请求错误: {}", masked_error)
+            ErrorType::JsonRpcError {
+                method,
+                code,
+                message,
+                data,
+            } => {
+                let mut text = match (*code, lang) {
+                    (METHOD_NOT_FOUND, Language::Zh) => {
+                        format!("Komari 不支持方法 {method}，请检查 Komari 版本是否过旧")
+                    }
+                    (METHOD_NOT_FOUND, Language::En) => format!(
+                        "Komari does not support method {method}, please check whether Komari is outdated"
+                    ),
+                    (_, Language::Zh) => format!("Komari 返回错误 ({method}): [{code}] {message}"),
+                    (_, Language::En) => {
+                        format!("Komari returned an error ({method}): [{code}] {message}")
+                    }
+                };
+                if let Some(data) = data {
+                    text.push_str(&format!(" ({data})"));
+                }
+                text
             }
-            ErrorType::JsonParseError { error } => {
-                write!(f, "JSON 解析错误: {}", error)
+            ErrorType::InstanceUnreachable { since } => {
+                let elapsed = settings.format_duration(since.elapsed().unwrap_or_default());
+                match lang {
+                    Language::Zh => format!("Komari 实例无法访问 (自 {elapsed} 前)，请稍后再试"),
+                    Language::En => format!(
+                        "Komari instance unreachable (for {elapsed}), please try again later"
+                    ),
+                }
             }
-            ErrorType::UnableToFindServerByUUID => {
-                write!(
-                    f,
-                    "找不到指定 UUID 的服务器，请检查是否在 Komari 后台新建机器后，未连接上报导致无数据"
+            ErrorType::ForbiddenTarget { host } => format!(
+                "{}: {host}",
+                lang.pick(
+                    "出于安全考虑，不允许访问本机、内网或保留地址",
+                    "For security reasons, local, private and reserved addresses are not allowed"
                 )
-            }
+            ),
+            ErrorType::UnableToFindServerByUUID => lang
+                .pick(
+                    "找不到指定 UUID 的服务器，请检查是否在 Komari 后台新建机器后，未连接上报导致无数据",
+                    "No server found with this UUID, check whether the node was added in Komari but never reported any data",
+                )
+                .to_string(),
             ErrorType::GeneralError { error } => {
-                write!(f, "发生错误: {}", error)
+                format!("{}: {error}", lang.pick("发生错误", "Error"))
             }
         }
     }
 }
+
+impl std::fmt::Display for ErrorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(&Settings::default()))
+    }
+}
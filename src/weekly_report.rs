@@ -0,0 +1,67 @@
+use crate::TelegramId;
+use crate::db::{DB_POOL, query_due_weekly_reports, update_weekly_report_at};
+use crate::json_rpc::outdated::{SYSTEM_CHANGE_WINDOW, outdated_report};
+use crate::settings::load_settings;
+use crate::utils::msg_fixer;
+use chrono::Utc;
+use log::{error, info};
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+
+/// 检查是否有待发送报告的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 定时发送每周版本报告，报告发送到通知会话，未设置时发送到私聊
+pub async fn run(bot: Bot) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let Some(db) = DB_POOL.get() else {
+            continue;
+        };
+
+        let now = Utc::now().timestamp();
+        let due =
+            match query_due_weekly_reports(db, now, SYSTEM_CHANGE_WINDOW.as_secs() as i64).await {
+                Ok(due) => due,
+                Err(e) => {
+                    error!("无法查询每周版本报告: {e}");
+                    continue;
+                }
+            };
+
+        for telegram_id in due {
+            send_report(&bot, telegram_id).await;
+
+            // 发送失败时同样等待下一周，避免每小时重复尝试
+            if let Err(e) = update_weekly_report_at(db, telegram_id, now).await {
+                error!("无法更新 {telegram_id} 的每周版本报告时间: {e}");
+            }
+        }
+    }
+}
+
+async fn send_report(bot: &Bot, telegram_id: TelegramId) {
+    let settings = load_settings(telegram_id, ChatId(telegram_id)).await;
+    let chat_id = settings.alert_chat.unwrap_or(ChatId(telegram_id));
+
+    let message = match outdated_report(telegram_id, &settings).await {
+        Ok(message) => message,
+        Err(e) => {
+            error!("无法生成 {telegram_id} 的每周版本报告: {e}");
+            return;
+        }
+    };
+
+    match bot
+        .send_message(chat_id, msg_fixer(message))
+        .parse_mode(ParseMode::MarkdownV2)
+        .await
+    {
+        Ok(_) => info!("已发送 {telegram_id} 的每周版本报告"),
+        Err(e) => error!("发送 {telegram_id} 的每周版本报告失败: {e}"),
+    }
+}